@@ -11,6 +11,29 @@ export type AIType = 'simple' | 'advanced';
 export type AIDifficulty = 'easy' | 'medium' | 'hard';
 export type AILevel = 1 | 2 | 3 | 4 | 5 | 6;
 
+/**
+ * Search progress reported by the WASM AI while it thinks
+ */
+export interface SearchInfo {
+  multipv: number;
+  depth: number;
+  seldepth: number;
+  score: { type: 'cp' | 'mate'; value: number };
+  bound: 'exact' | 'lower' | 'upper';
+  nodes: number;
+  nps: number;
+  timeMs: number;
+  hashfull: number;
+  pv: Array<{
+    from: { row: number; col: number } | null;
+    to: { row: number; col: number };
+    pieceType: string;
+    promoted: boolean;
+    promotion: boolean;
+    promoteTo?: string;
+  }>;
+}
+
 interface AIServiceConfig {
   type: AIType;
   difficulty?: AIDifficulty;  // Legacy support
@@ -23,6 +46,7 @@ export class AIService {
   private config: AIServiceConfig;
   private isReady: boolean = false;
   private pendingResolve: ((move: Move | null) => void) | null = null;
+  private infoListener: ((info: SearchInfo) => void) | null = null;
 
   constructor(config: AIServiceConfig) {
     this.config = config;
@@ -68,6 +92,8 @@ export class AIService {
             resolve();
           } else if (type === 'MOVE') {
             this.handleMoveResponse(e.data.move);
+          } else if (type === 'INFO') {
+            this.infoListener?.(JSON.parse(e.data.info));
           } else if (type === 'CHECKMATE') {
             // AI has no legal moves - this means the AI is checkmated
             console.log('AI has no legal moves (checkmated)');
@@ -188,6 +214,13 @@ export class AIService {
     }
   }
 
+  /**
+   * Listen to search progress (pass null to stop)
+   */
+  onInfo(listener: ((info: SearchInfo) => void) | null): void {
+    this.infoListener = listener;
+  }
+
   /**
    * Update AI level
    */
@@ -202,6 +235,17 @@ export class AIService {
     }
   }
 
+  /**
+   * Start a new game (drops what the AI learnt in the previous one)
+   */
+  newGame(): void {
+    if (this.worker && this.isReady) {
+      this.worker.postMessage({
+        type: 'NEW_GAME',
+      });
+    }
+  }
+
   /**
    * Update AI difficulty (legacy)
    */
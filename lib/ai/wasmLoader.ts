@@ -9,16 +9,29 @@ let loadError: Error | null = null;
 
 export type WasmAI = {
   get_best_move(boardJson: string): string;
+  get_best_lines(boardJson: string, count: number): string;
   set_level(level: number): void;
   get_level(): number;
   set_depth(depth: number): void;
   get_depth(): number;
+  set_rules(rulesJson: string): void;
+  clear_rules(): void;
+  new_game(): void;
+  clear_hash(): void;
+  set_hash_mb(mb: number): void;
+  get_hash_mb(): number;
+  memory_estimate(): number;
+  export_hash(): Uint8Array;
+  import_hash(data: Uint8Array): void;
+  set_info_callback(callback: ((infoJson: string) => void) | null): void;
+  hashfull(): number;
 };
 
 export interface WasmModule {
   WasmAI: {
     new(level: number): WasmAI;
   };
+  bench(depth: number): string;
 }
 
 /**
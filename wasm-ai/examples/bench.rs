@@ -0,0 +1,59 @@
+//! Native search benchmark: `cargo run --release --example bench [depth]`
+//!
+//! Also counts heap allocations made during the run and fails if they grow
+//! faster than the iteration count: search itself must not allocate per node.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+struct CountingAlloc;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+fn main() {
+    let depth = std::env::args()
+        .nth(1)
+        .and_then(|arg| arg.parse().ok())
+        .unwrap_or(5);
+
+    let baseline = count_allocations(|| {
+        let _ = hybrid_board_ai_wasm::bench(1);
+    });
+    let allocations = count_allocations(|| match hybrid_board_ai_wasm::bench(depth) {
+        Ok(report) => println!("{}", report),
+        Err(e) => eprintln!("bench failed: {:?}", e),
+    });
+    println!("allocations={}", allocations);
+
+    // Setup and per-iteration reporting allocate; nodes must not
+    let limit = baseline + ALLOCATIONS_PER_ITERATION * depth as usize;
+    assert!(
+        allocations <= limit,
+        "allocations grew with depth: {} > {}",
+        allocations,
+        limit
+    );
+}
+
+/// Allowance for info reports and PV copies, summed over all positions
+const ALLOCATIONS_PER_ITERATION: usize = 64;
+
+fn count_allocations(f: impl FnOnce()) -> usize {
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    f();
+    ALLOCATIONS.load(Ordering::Relaxed) - before
+}
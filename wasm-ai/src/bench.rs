@@ -0,0 +1,144 @@
+//! Fixed-depth search over a set of positions, used to compare nodes per
+//! second between builds (`cargo run --release --example bench`)
+
+use crate::board::Board;
+use crate::config::AIConfig;
+use crate::history::History;
+use crate::platform::now_ms;
+use crate::search::find_best_move;
+use crate::tt::TranspositionTable;
+use crate::types::*;
+use wasm_bindgen::JsValue;
+
+/// Board rows in the editor's string format (upper case = player 1) and
+/// optional rules JSON
+const POSITIONS: &[(&str, &[&str], Option<&str>)] = &[
+    (
+        "shogi",
+        &[
+            "l n s g k g s n l",
+            ". r . . . . . b .",
+            "p p p p p p p p p",
+            ". . . . . . . . .",
+            ". . . . . . . . .",
+            ". . . . . . . . .",
+            "P P P P P P P P P",
+            ". B . . . . . R .",
+            "L N S G K G S N L",
+        ],
+        None,
+    ),
+    (
+        "chess",
+        &[
+            "cr cn cb cq ck cb cn cr",
+            "cp cp cp cp cp cp cp cp",
+            ". . . . . . . .",
+            ". . . . . . . .",
+            ". . . . . . . .",
+            ". . . . . . . .",
+            "CP CP CP CP CP CP CP CP",
+            "CR CN CB CQ CK CB CN CR",
+        ],
+        Some(r#"{"hands": {"player1": false, "player2": false}}"#),
+    ),
+    (
+        "hybrid",
+        &[
+            "cr cn cb . ck cq cb cn cr",
+            "cp cp cp cp cp cp cp cp cp",
+            ". . . . . . . . .",
+            ". . . . . . . . .",
+            ". . . . . . . . .",
+            ". . . . . . . . .",
+            "P P P P P P P P P",
+            ". B . . . . . R .",
+            "L N S G K G S N L",
+        ],
+        Some(r#"{"hands": {"player2": false}}"#),
+    ),
+];
+
+pub struct BenchResult {
+    pub name: &'static str,
+    pub nodes: usize,
+    pub time_ms: f64,
+}
+
+/// Search every benchmark position to `depth` with the strongest settings
+pub fn run(depth: u8) -> Result<Vec<BenchResult>, JsValue> {
+    let mut config = AIConfig::from_level(6);
+    config.max_depth = depth;
+    config.timeout_ms = u32::MAX;
+
+    let mut results = Vec::new();
+    for &(name, rows, rules) in POSITIONS {
+        let board = start_board(rows, rules)?;
+
+        // A fresh table and history per position keep the runs independent
+        let mut tt = TranspositionTable::new(config.tt_size_mb);
+        let mut history = History::new(board.rows(), board.cols());
+        let start = now_ms();
+        let result = find_best_move(&board, 1, &config, &mut tt, &mut history, &mut |_| {})?;
+        results.push(BenchResult {
+            name,
+            nodes: result.nodes,
+            time_ms: now_ms() - start,
+        });
+    }
+    Ok(results)
+}
+
+/// Board named `name` in the benchmark set, with player 1 to move
+#[cfg(test)]
+pub fn position(name: &str) -> Board {
+    let &(_, rows, rules) = POSITIONS
+        .iter()
+        .find(|(position, ..)| *position == name)
+        .expect("unknown bench position");
+    start_board(rows, rules).expect("bench position is valid")
+}
+
+pub(crate) fn start_board(rows: &[&str], rules: Option<&str>) -> Result<Board, JsValue> {
+    let state = GameStateInput {
+        board: rows.iter().map(|row| parse_row(row)).collect(),
+        current_player: 1,
+        hands: None,
+        rules: rules
+            .map(serde_json::from_str)
+            .transpose()
+            .map_err(|e| to_js_error(&e.to_string()))?,
+        move_count: 0,
+        checks_given: [0; 2],
+    };
+    Board::from_game_state(&state, None)
+}
+
+fn parse_row(row: &str) -> Vec<Option<Piece>> {
+    row.split_whitespace()
+        .map(|cell| {
+            let piece_type = match cell.to_uppercase().as_str() {
+                "K" => PieceType::King,
+                "R" => PieceType::Rook,
+                "B" => PieceType::Bishop,
+                "G" => PieceType::Gold,
+                "S" => PieceType::Silver,
+                "N" => PieceType::Knight,
+                "L" => PieceType::Lance,
+                "P" => PieceType::Pawn,
+                "CK" => PieceType::ChessKing,
+                "CQ" => PieceType::ChessQueen,
+                "CR" => PieceType::ChessRook,
+                "CB" => PieceType::ChessBishop,
+                "CN" => PieceType::ChessKnight,
+                "CP" => PieceType::ChessPawn,
+                _ => return None,
+            };
+            Some(Piece {
+                piece_type,
+                player: if cell == cell.to_uppercase() { 1 } else { 2 },
+                promoted: false,
+            })
+        })
+        .collect()
+}
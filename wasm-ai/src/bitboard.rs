@@ -0,0 +1,551 @@
+use crate::rules::{RuleSet, MAX_BOARD_SIZE};
+use crate::types::*;
+use std::ops::{BitAnd, BitAndAssign, BitOr, BitOrAssign, BitXorAssign, Not};
+
+/// Square index used by bitboards: `row * MAX_BOARD_SIZE + col`
+pub type Square = usize;
+
+/// Squares on the largest supported board
+pub const NUM_SQUARES: usize = MAX_BOARD_SIZE * MAX_BOARD_SIZE;
+
+pub fn square(pos: Position) -> Square {
+    pos.row * MAX_BOARD_SIZE + pos.col
+}
+
+pub fn position(sq: Square) -> Position {
+    Position {
+        row: sq / MAX_BOARD_SIZE,
+        col: sq % MAX_BOARD_SIZE,
+    }
+}
+
+/// Set of squares on boards up to 11x11 (121 of the 128 bits are used)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Bitboard(pub u128);
+
+impl Bitboard {
+    pub const EMPTY: Bitboard = Bitboard(0);
+
+    pub fn contains(self, sq: Square) -> bool {
+        self.0 & (1u128 << sq) != 0
+    }
+
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn count(self) -> u32 {
+        self.0.count_ones()
+    }
+
+    pub fn toggle(&mut self, sq: Square) {
+        self.0 ^= 1u128 << sq;
+    }
+
+    /// Iterate over the squares in ascending order
+    pub fn squares(self) -> Squares {
+        Squares(self.0)
+    }
+}
+
+pub struct Squares(u128);
+
+impl Iterator for Squares {
+    type Item = Square;
+
+    fn next(&mut self) -> Option<Square> {
+        if self.0 == 0 {
+            return None;
+        }
+        let sq = self.0.trailing_zeros() as Square;
+        self.0 &= self.0 - 1;
+        Some(sq)
+    }
+}
+
+impl BitAnd for Bitboard {
+    type Output = Bitboard;
+    fn bitand(self, rhs: Bitboard) -> Bitboard {
+        Bitboard(self.0 & rhs.0)
+    }
+}
+
+impl BitOr for Bitboard {
+    type Output = Bitboard;
+    fn bitor(self, rhs: Bitboard) -> Bitboard {
+        Bitboard(self.0 | rhs.0)
+    }
+}
+
+impl Not for Bitboard {
+    type Output = Bitboard;
+    fn not(self) -> Bitboard {
+        Bitboard(!self.0)
+    }
+}
+
+impl BitAndAssign for Bitboard {
+    fn bitand_assign(&mut self, rhs: Bitboard) {
+        self.0 &= rhs.0;
+    }
+}
+
+impl BitOrAssign for Bitboard {
+    fn bitor_assign(&mut self, rhs: Bitboard) {
+        self.0 |= rhs.0;
+    }
+}
+
+impl BitXorAssign for Bitboard {
+    fn bitxor_assign(&mut self, rhs: Bitboard) {
+        self.0 ^= rhs.0;
+    }
+}
+
+/// Indices into `ALL_DIRECTIONS`; the last four run towards higher squares
+const ORTHOGONAL: [usize; 4] = [1, 3, 4, 6];
+const DIAGONAL: [usize; 4] = [0, 2, 5, 7];
+const NORTH: usize = 1;
+const SOUTH: usize = 6;
+
+const ALL_DIRECTIONS: [(i32, i32); 8] = [
+    (-1, -1),
+    (-1, 0),
+    (-1, 1),
+    (0, -1),
+    (0, 1),
+    (1, -1),
+    (1, 0),
+    (1, 1),
+];
+const CHESS_KNIGHT: [(i32, i32); 8] = [
+    (-2, -1),
+    (-2, 1),
+    (-1, -2),
+    (-1, 2),
+    (1, -2),
+    (1, 2),
+    (2, -1),
+    (2, 1),
+];
+
+/// Squares from each square to the edge of the largest board, per direction
+static RAYS: [[Bitboard; NUM_SQUARES]; 8] = build_rays();
+
+const fn build_rays() -> [[Bitboard; NUM_SQUARES]; 8] {
+    let mut rays = [[Bitboard::EMPTY; NUM_SQUARES]; 8];
+    let mut dir = 0;
+    while dir < ALL_DIRECTIONS.len() {
+        let (dr, dc) = ALL_DIRECTIONS[dir];
+        let mut sq = 0;
+        while sq < NUM_SQUARES {
+            let mut row = (sq / MAX_BOARD_SIZE) as i32 + dr;
+            let mut col = (sq % MAX_BOARD_SIZE) as i32 + dc;
+            let mut bits = 0u128;
+            while row >= 0 && row < MAX_BOARD_SIZE as i32 && col >= 0 && col < MAX_BOARD_SIZE as i32
+            {
+                bits |= 1u128 << (row as usize * MAX_BOARD_SIZE + col as usize);
+                row += dr;
+                col += dc;
+            }
+            rays[dir][sq] = Bitboard(bits);
+            sq += 1;
+        }
+        dir += 1;
+    }
+    rays
+}
+
+/// Piece placement as bitboards, per piece type and per player
+#[derive(Debug, Clone)]
+pub struct BitPosition {
+    pub rows: usize,
+    pub cols: usize,
+    pub pieces: [Bitboard; NUM_PIECE_TYPES], // Both players
+    pub players: [Bitboard; 2],              // Index 0 = Player 1
+    pub promoted: Bitboard,
+    pub hands: [[u8; NUM_PIECE_TYPES]; 2],
+    pub current_player: Player,
+    /// Squares that exist on this board
+    pub on_board: Bitboard,
+}
+
+impl BitPosition {
+    pub fn empty(rows: usize, cols: usize, current_player: Player) -> Self {
+        Self {
+            rows,
+            cols,
+            pieces: [Bitboard::EMPTY; NUM_PIECE_TYPES],
+            players: [Bitboard::EMPTY; 2],
+            promoted: Bitboard::EMPTY,
+            hands: [[0; NUM_PIECE_TYPES]; 2],
+            current_player,
+            on_board: (0..rows).fold(Bitboard::EMPTY, |mask, row| {
+                mask | Bitboard(((1u128 << cols) - 1) << (row * MAX_BOARD_SIZE))
+            }),
+        }
+    }
+
+    pub fn from_game_state(state: &GameStateInput) -> Result<Self, String> {
+        let rows = state.board.len();
+        let cols = state.board.first().map_or(0, |row| row.len());
+        if rows == 0 || rows > MAX_BOARD_SIZE || cols == 0 || cols > MAX_BOARD_SIZE {
+            return Err("Invalid board dimensions".to_string());
+        }
+
+        if !(1..=2).contains(&state.current_player) {
+            return Err("currentPlayer must be 1 or 2".to_string());
+        }
+
+        let mut bits = Self::empty(rows, cols, state.current_player);
+        for (row, cells) in state.board.iter().enumerate() {
+            if cells.len() != cols {
+                return Err("Invalid board dimensions".to_string());
+            }
+            for (col, cell) in cells.iter().enumerate() {
+                if let Some(piece) = cell {
+                    if !(1..=2).contains(&piece.player) {
+                        return Err("Piece player must be 1 or 2".to_string());
+                    }
+                    bits.toggle_piece(square(Position { row, col }), piece);
+                }
+            }
+        }
+
+        if let Some(ref hand_pieces) = state.hands {
+            // Hand counts are bounded by the number of pieces in play
+            let in_hand = hand_pieces.player1.len() + hand_pieces.player2.len();
+            if in_hand + bits.occupied().count() as usize > NUM_SQUARES {
+                return Err("Too many pieces in hand".to_string());
+            }
+            for (player_idx, hand) in [&hand_pieces.player1, &hand_pieces.player2]
+                .into_iter()
+                .enumerate()
+            {
+                for &piece_type in hand {
+                    bits.hands[player_idx][piece_type.index()] += 1;
+                }
+            }
+        }
+
+        Ok(bits)
+    }
+
+    /// The position in the JS input format, with hands listed in
+    /// `PieceType::ALL` order (rules, move count and checks are left unset)
+    pub fn to_game_state(&self) -> GameStateInput {
+        let board = (0..self.rows)
+            .map(|row| {
+                (0..self.cols)
+                    .map(|col| self.piece_at(square(Position { row, col })))
+                    .collect()
+            })
+            .collect();
+
+        let hand = |player_idx: usize| {
+            PieceType::ALL
+                .iter()
+                .flat_map(|&pt| {
+                    std::iter::repeat_n(pt, self.hands[player_idx][pt.index()] as usize)
+                })
+                .collect()
+        };
+
+        GameStateInput {
+            board,
+            current_player: self.current_player,
+            hands: Some(HandPieces {
+                player1: hand(0),
+                player2: hand(1),
+            }),
+            rules: None,
+            move_count: 0,
+            checks_given: [0; 2],
+        }
+    }
+
+    /// Add or remove a piece on a square
+    pub fn toggle_piece(&mut self, sq: Square, piece: &Piece) {
+        self.pieces[piece.piece_type.index()].toggle(sq);
+        self.players[(piece.player - 1) as usize].toggle(sq);
+        if piece.promoted {
+            self.promoted.toggle(sq);
+        }
+    }
+
+    pub fn occupied(&self) -> Bitboard {
+        self.players[0] | self.players[1]
+    }
+
+    /// Squares of column `col`
+    pub fn file(&self, col: usize) -> Bitboard {
+        (0..self.rows).fold(Bitboard::EMPTY, |file, row| {
+            file | Bitboard(1u128 << (row * MAX_BOARD_SIZE + col))
+        })
+    }
+
+    pub fn piece_at(&self, sq: Square) -> Option<Piece> {
+        let player = if self.players[0].contains(sq) {
+            1
+        } else if self.players[1].contains(sq) {
+            2
+        } else {
+            return None;
+        };
+        let piece_type = PieceType::ALL
+            .into_iter()
+            .find(|pt| self.pieces[pt.index()].contains(sq))?;
+
+        Some(Piece {
+            piece_type,
+            player,
+            promoted: self.promoted.contains(sq),
+        })
+    }
+
+    /// Squares a piece of `player` moving like `movement` attacks from `sq`,
+    /// with sliders stopped by the first square in `occupied`
+    pub fn attacks(
+        &self,
+        sq: Square,
+        movement: Movement,
+        player: Player,
+        occupied: Bitboard,
+    ) -> Bitboard {
+        let forward = if player == 1 { -1 } else { 1 };
+        let ahead = if player == 1 { NORTH } else { SOUTH };
+        match movement {
+            Movement::King => self.steps(sq, &ALL_DIRECTIONS),
+            Movement::ChessQueen => {
+                self.slides(sq, &ORTHOGONAL, occupied) | self.slides(sq, &DIAGONAL, occupied)
+            }
+            Movement::Rook | Movement::ChessRook => self.slides(sq, &ORTHOGONAL, occupied),
+            Movement::Bishop | Movement::ChessBishop => self.slides(sq, &DIAGONAL, occupied),
+            Movement::Dragon => {
+                self.slides(sq, &ORTHOGONAL, occupied) | self.steps_in(sq, &DIAGONAL)
+            }
+            Movement::Horse => {
+                self.slides(sq, &DIAGONAL, occupied) | self.steps_in(sq, &ORTHOGONAL)
+            }
+            Movement::ChessKnight => self.steps(sq, &CHESS_KNIGHT),
+            Movement::Knight => self.steps(sq, &[(forward * 2, -1), (forward * 2, 1)]),
+            Movement::Lance => self.slides(sq, &[ahead], occupied),
+            Movement::Pawn => self.steps(sq, &[(forward, 0)]),
+            Movement::ChessPawn => self.steps(sq, &[(forward, -1), (forward, 1)]),
+            Movement::Gold => self.steps(
+                sq,
+                &[
+                    (forward, -1),
+                    (forward, 0),
+                    (forward, 1),
+                    (0, -1),
+                    (0, 1),
+                    (-forward, 0),
+                ],
+            ),
+            Movement::Silver => self.steps(
+                sq,
+                &[
+                    (forward, -1),
+                    (forward, 0),
+                    (forward, 1),
+                    (-forward, -1),
+                    (-forward, 1),
+                ],
+            ),
+        }
+    }
+
+    /// Pieces of `by_player` that attack `sq`
+    ///
+    /// Uses the reverse-attack trick: a piece on X attacks `sq` exactly when
+    /// the same movement of the other player attacks X from `sq`.
+    pub fn attackers_to(&self, sq: Square, by_player: Player, rules: &RuleSet) -> Bitboard {
+        self.attackers_with(sq, by_player, rules, self.occupied())
+    }
+
+    /// Pieces of `by_player` within `occupied` that attack `sq` when only
+    /// `occupied` blocks sliders (pieces removed from it expose x-rays)
+    pub fn attackers_with(
+        &self,
+        sq: Square,
+        by_player: Player,
+        rules: &RuleSet,
+        occupied: Bitboard,
+    ) -> Bitboard {
+        let own = self.players[(by_player - 1) as usize] & occupied;
+        let mut attackers = Bitboard::EMPTY;
+
+        for piece_type in PieceType::ALL {
+            let pieces = self.pieces[piece_type.index()] & own;
+            if pieces.is_empty() {
+                continue;
+            }
+
+            let plain = pieces & !self.promoted;
+            if !plain.is_empty() {
+                attackers |=
+                    self.attacks(sq, piece_type.movement(), 3 - by_player, occupied) & plain;
+            }
+
+            let promoted = pieces & self.promoted;
+            if !promoted.is_empty() {
+                let movement = rules.movement(&Piece {
+                    piece_type,
+                    player: by_player,
+                    promoted: true,
+                });
+                attackers |= self.attacks(sq, movement, 3 - by_player, occupied) & promoted;
+            }
+        }
+
+        attackers
+    }
+
+    pub fn is_attacked(&self, sq: Square, by_player: Player, rules: &RuleSet) -> bool {
+        !self.attackers_to(sq, by_player, rules).is_empty()
+    }
+
+    /// Pieces of `player` whose type is royal under `rules`
+    pub fn royals(&self, player: Player, rules: &RuleSet) -> Bitboard {
+        let mut royals = Bitboard::EMPTY;
+        for &piece_type in &rules.royal_pieces {
+            royals |= self.pieces[piece_type.index()];
+        }
+        royals & self.players[(player - 1) as usize]
+    }
+
+    fn offset(&self, sq: Square, dr: i32, dc: i32) -> Option<Square> {
+        let pos = position(sq);
+        let row = pos.row as i32 + dr;
+        let col = pos.col as i32 + dc;
+        if row >= 0 && row < self.rows as i32 && col >= 0 && col < self.cols as i32 {
+            Some(row as usize * MAX_BOARD_SIZE + col as usize)
+        } else {
+            None
+        }
+    }
+
+    fn steps(&self, sq: Square, deltas: &[(i32, i32)]) -> Bitboard {
+        let mut result = Bitboard::EMPTY;
+        for &(dr, dc) in deltas {
+            if let Some(to) = self.offset(sq, dr, dc) {
+                result.toggle(to);
+            }
+        }
+        result
+    }
+
+    /// Single steps in the given `ALL_DIRECTIONS` indices
+    fn steps_in(&self, sq: Square, directions: &[usize]) -> Bitboard {
+        let mut result = Bitboard::EMPTY;
+        for &dir in directions {
+            let (dr, dc) = ALL_DIRECTIONS[dir];
+            if let Some(to) = self.offset(sq, dr, dc) {
+                result.toggle(to);
+            }
+        }
+        result
+    }
+
+    /// Sliding attacks up to and including the first blocker in each of the
+    /// given `ALL_DIRECTIONS` indices
+    fn slides(&self, sq: Square, directions: &[usize], occupied: Bitboard) -> Bitboard {
+        let mut result = Bitboard::EMPTY;
+        for &dir in directions {
+            let ray = RAYS[dir][sq] & self.on_board;
+            let blockers = ray & occupied;
+            result |= if blockers.is_empty() {
+                ray
+            } else {
+                let first = if dir >= 4 {
+                    blockers.0.trailing_zeros()
+                } else {
+                    127 - blockers.0.leading_zeros()
+                };
+                ray & !RAYS[dir][first as usize]
+            };
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn piece(piece_type: PieceType, player: Player, promoted: bool) -> Option<Piece> {
+        Some(Piece {
+            piece_type,
+            player,
+            promoted,
+        })
+    }
+
+    fn state(
+        board: Vec<Vec<Option<Piece>>>,
+        current_player: Player,
+        hands: HandPieces,
+    ) -> GameStateInput {
+        GameStateInput {
+            board,
+            current_player,
+            hands: Some(hands),
+            rules: None,
+            move_count: 0,
+            checks_given: [0; 2],
+        }
+    }
+
+    #[test]
+    fn game_state_round_trips() {
+        let mut board = vec![vec![None; 9]; 7];
+        board[0][4] = piece(PieceType::King, 2, false);
+        board[1][1] = piece(PieceType::Rook, 2, true);
+        board[3][8] = piece(PieceType::ChessPawn, 2, false);
+        board[4][0] = piece(PieceType::ChessQueen, 1, false);
+        board[5][2] = piece(PieceType::Pawn, 1, true);
+        board[6][4] = piece(PieceType::ChessKing, 1, false);
+        let state = state(
+            board,
+            2,
+            HandPieces {
+                player1: vec![PieceType::Bishop, PieceType::Pawn, PieceType::Pawn],
+                player2: vec![PieceType::Gold, PieceType::Lance],
+            },
+        );
+
+        let bits = BitPosition::from_game_state(&state).unwrap();
+        assert_eq!(bits.hands[0][PieceType::Pawn.index()], 2);
+        assert!(bits.promoted.contains(square(Position { row: 1, col: 1 })));
+
+        let round_trip = bits.to_game_state();
+        assert_eq!(round_trip.board, state.board);
+        assert_eq!(round_trip.current_player, state.current_player);
+        let (hands, expected) = (round_trip.hands.unwrap(), state.hands.unwrap());
+        assert_eq!(hands.player1, expected.player1);
+        assert_eq!(hands.player2, expected.player2);
+    }
+
+    #[test]
+    fn from_game_state_rejects_bad_input() {
+        let empty_hands = || HandPieces {
+            player1: Vec::new(),
+            player2: Vec::new(),
+        };
+        let mut board = vec![vec![None; 9]; 9];
+
+        // 300 pawns overflow a u8 count, and more than fit on the board
+        let hands = HandPieces {
+            player1: vec![PieceType::Pawn; 300],
+            player2: Vec::new(),
+        };
+        assert!(BitPosition::from_game_state(&state(board.clone(), 1, hands)).is_err());
+
+        assert!(BitPosition::from_game_state(&state(board.clone(), 0, empty_hands())).is_err());
+        assert!(BitPosition::from_game_state(&state(board.clone(), 3, empty_hands())).is_err());
+
+        board[4][4] = piece(PieceType::Gold, 3, false);
+        assert!(BitPosition::from_game_state(&state(board, 1, empty_hands())).is_err());
+    }
+}
@@ -1,128 +1,309 @@
+use crate::bitboard::{square, BitPosition, NUM_SQUARES};
+use crate::eval::EvalTerms;
+use crate::moves::is_in_check;
+use crate::rules::{RuleSet, WinCondition};
 use crate::types::*;
+use crate::zobrist::get_zobrist;
+use std::rc::Rc;
 use wasm_bindgen::JsValue;
 
-const _BOARD_SIZE: usize = 9;
-
 /// Board representation
+///
+/// Squares and bitboards live in fixed-size arrays, so cloning a board never
+/// allocates (the rules are shared through an `Rc`).
 #[derive(Debug, Clone)]
 pub struct Board {
-    pub cells: Vec<Vec<Option<Piece>>>,
-    pub current_player: Player,
-    pub hands: [Vec<PieceType>; 2], // Index 0 = Player 1, Index 1 = Player 2
+    squares: [Option<Piece>; NUM_SQUARES], // Indexed by `bitboard::square`
+    pub rules: Rc<RuleSet>,
+    pub move_count: u32,
+    pub checks_given: [u8; 2], // Only tracked when three-check is in force
+    pub royals_at_start: [u8; 2],
+    pub bits: BitPosition, // Kept in sync with `squares`; owns hands and side to move
+    pub hash: u64,         // Zobrist key, updated by make/unmake
+    pub terms: EvalTerms,  // Material and PST sums, updated by make/unmake
 }
 
 impl Board {
-    pub fn from_game_state(state: &GameStateInput) -> Result<Self, JsValue> {
-        let size = state.board.len();
-        if size == 0 || state.board[0].len() != size {
+    /// Build a board from the JS input; rules in the input take precedence
+    /// over `default_rules`
+    pub fn from_game_state(
+        state: &GameStateInput,
+        default_rules: Option<&RuleSet>,
+    ) -> Result<Self, JsValue> {
+        let rows = state.board.len();
+        let cols = state.board.first().map_or(0, |row| row.len());
+        if rows == 0 || cols == 0 || state.board.iter().any(|row| row.len() != cols) {
             return Err(to_js_error("Invalid board dimensions"));
         }
 
-        let mut hands = [Vec::new(), Vec::new()];
-        if let Some(ref hand_pieces) = state.hands {
-            hands[0] = hand_pieces.player1.clone();
-            hands[1] = hand_pieces.player2.clone();
+        let mut rules = state
+            .rules
+            .clone()
+            .or_else(|| default_rules.cloned())
+            .unwrap_or_default()
+            .resolve(rows, cols)
+            .map_err(|e: String| to_js_error(&e))?;
+
+        // Reaching the limit ends the game, so a live position is below it
+        if state.checks_given.iter().any(|&n| n >= rules.check_limit) {
+            return Err(to_js_error("checksGiven is out of range"));
+        }
+
+        let bits = BitPosition::from_game_state(state).map_err(|e| to_js_error(&e))?;
+        let royals_at_start = [1, 2].map(|player| bits.royals(player, &rules).count() as u8);
+
+        // Kingless setups cannot be won by mate or king goals alone
+        if royals_at_start.contains(&0) && !rules.wins_by(WinCondition::CaptureAll) {
+            rules.win_conditions.push(WinCondition::CaptureAll);
+        }
+
+        let mut squares = [None; NUM_SQUARES];
+        for (row, cells) in state.board.iter().enumerate() {
+            for (col, cell) in cells.iter().enumerate() {
+                squares[square(Position { row, col })] = *cell;
+            }
         }
 
-        Ok(Self {
-            cells: state.board.clone(),
-            current_player: state.current_player,
-            hands,
-        })
+        let mut board = Self {
+            squares,
+            rules: Rc::new(rules),
+            move_count: state.move_count,
+            checks_given: state.checks_given,
+            royals_at_start,
+            bits,
+            hash: 0,
+            terms: EvalTerms::default(),
+        };
+        board.hash = get_zobrist().hash(&board);
+        board.terms = EvalTerms::compute(&board);
+
+        Ok(board)
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rules.rows
     }
 
-    pub fn size(&self) -> usize {
-        self.cells.len()
+    pub fn cols(&self) -> usize {
+        self.rules.cols
     }
 
     pub fn get(&self, pos: Position) -> Option<&Piece> {
-        self.cells.get(pos.row)?.get(pos.col)?.as_ref()
+        self.square_of(pos).and_then(|sq| self.squares[sq].as_ref())
+    }
+
+    pub fn current_player(&self) -> Player {
+        self.bits.current_player
+    }
+
+    /// Pieces in hand, counted per piece type (indexed by `PieceType::index`)
+    pub fn hand(&self, player: Player) -> &[u8; NUM_PIECE_TYPES] {
+        &self.bits.hands[(player - 1) as usize]
+    }
+
+    pub fn hand_count(&self, player: Player, piece_type: PieceType) -> u8 {
+        self.hand(player)[piece_type.index()]
+    }
+
+    /// Square index of an on-board position
+    fn square_of(&self, pos: Position) -> Option<usize> {
+        (pos.row < self.rows() && pos.col < self.cols()).then(|| square(pos))
+    }
+
+    /// Whether the side to move puts a captured piece of this type in hand
+    fn keeps_in_hand(&self, piece_type: PieceType) -> bool {
+        self.rules.has_hand(self.current_player()) && !self.rules.is_royal(piece_type)
+    }
+
+    fn counts_checks(&self) -> bool {
+        self.rules.wins_by(WinCondition::ThreeCheck)
+    }
+
+    /// Whether `player` has given as many checks as the three-check limit
+    pub fn check_limit_reached(&self, player: Player) -> bool {
+        self.counts_checks() && self.checks_given[(player - 1) as usize] >= self.rules.check_limit
+    }
+
+    /// Place a piece on an empty square
+    fn put(&mut self, pos: Position, piece: Piece) -> Result<(), JsValue> {
+        let sq = self
+            .square_of(pos)
+            .ok_or_else(|| to_js_error("Invalid position"))?;
+        self.squares[sq] = Some(piece);
+        self.bits.toggle_piece(square(pos), &piece);
+        self.hash ^= get_zobrist().piece_key(&piece, pos);
+        self.terms.add_piece(&self.rules, &piece, pos);
+        Ok(())
     }
 
-    pub fn get_mut(&mut self, pos: Position) -> Option<&mut Option<Piece>> {
-        self.cells.get_mut(pos.row)?.get_mut(pos.col)
+    /// Remove and return the piece on a square
+    fn take(&mut self, pos: Position) -> Result<Option<Piece>, JsValue> {
+        let sq = self
+            .square_of(pos)
+            .ok_or_else(|| to_js_error("Invalid position"))?;
+        let piece = self.squares[sq].take();
+        if let Some(ref piece) = piece {
+            self.bits.toggle_piece(square(pos), piece);
+            self.hash ^= get_zobrist().piece_key(piece, pos);
+            self.terms.remove_piece(&self.rules, piece, pos);
+        }
+        Ok(piece)
+    }
+
+    fn add_to_hand(&mut self, player: Player, piece_type: PieceType) {
+        let count = &mut self.bits.hands[(player - 1) as usize][piece_type.index()];
+        *count += 1;
+        self.hash ^= get_zobrist().hand_key(player, piece_type, *count as usize);
+        self.terms.add_to_hand(&self.rules, player, piece_type);
+    }
+
+    fn remove_from_hand(&mut self, player: Player, piece_type: PieceType) -> Result<(), JsValue> {
+        let count = &mut self.bits.hands[(player - 1) as usize][piece_type.index()];
+        if *count == 0 {
+            return Err(to_js_error("Piece not in hand"));
+        }
+        self.hash ^= get_zobrist().hand_key(player, piece_type, *count as usize);
+        *count -= 1;
+        self.terms.remove_from_hand(&self.rules, player, piece_type);
+        Ok(())
+    }
+
+    /// Compare the incrementally updated state with a full recompute
+    fn debug_check(&self, after: &str) {
+        debug_assert_eq!(
+            self.hash,
+            get_zobrist().hash(self),
+            "hash drift after {}",
+            after
+        );
+        debug_assert_eq!(
+            self.terms,
+            EvalTerms::compute(self),
+            "eval terms drift after {}",
+            after
+        );
+        debug_assert!(
+            self.bits
+                .to_game_state()
+                .board
+                .iter()
+                .enumerate()
+                .all(|(row, cells)| cells
+                    .iter()
+                    .enumerate()
+                    .all(|(col, cell)| cell.as_ref() == self.get(Position { row, col }))),
+            "bitboards drift from squares after {}",
+            after
+        );
+    }
+
+    fn switch_player(&mut self) {
+        self.bits.current_player = 3 - self.bits.current_player;
+        self.hash ^= get_zobrist().side_key();
+    }
+
+    /// Pass the turn without moving (null-move pruning)
+    pub fn make_null_move(&mut self) {
+        self.switch_player();
     }
 
     /// Make a move on the board
     pub fn make_move(&mut self, m: &Move) -> Result<(), JsValue> {
-        // Capture piece if exists
-        if let Some(piece) = self.get(m.to) {
-            let captured_type = if piece.promoted {
-                // Demote before capturing
-                match piece.piece_type {
-                    PieceType::Rook => PieceType::Rook,
-                    PieceType::Bishop => PieceType::Bishop,
-                    PieceType::Silver => PieceType::Silver,
-                    PieceType::Knight => PieceType::Knight,
-                    PieceType::Lance => PieceType::Lance,
-                    PieceType::Pawn => PieceType::Pawn,
-                    _ => piece.piece_type,
-                }
-            } else {
-                piece.piece_type
-            };
+        let player = self.current_player();
 
-            self.hands[(self.current_player - 1) as usize].push(captured_type);
-        }
+        if m.is_drop() {
+            self.remove_from_hand(player, m.piece_type())?;
+            self.put(
+                m.to(),
+                Piece {
+                    piece_type: m.piece_type(),
+                    player,
+                    promoted: false,
+                },
+            )?;
+        } else {
+            // Capture piece if exists (promoted pieces return to hand demoted)
+            if let Some(captured) = self.take(m.to())? {
+                if self.keeps_in_hand(captured.piece_type) {
+                    self.add_to_hand(player, captured.piece_type);
+                }
+            }
 
-        // Move piece
-        if let Some(from_cell) = self.get_mut(m.from) {
-            let mut piece = from_cell
-                .take()
+            // Move piece
+            let mut piece = self
+                .take(m.from())?
                 .ok_or_else(|| to_js_error("No piece at from position"))?;
 
             // Apply promotion if needed
-            if m.promotion {
+            if m.is_promotion() {
                 piece.promoted = true;
             }
+            if let Some(promote_to) = m.promote_to() {
+                piece.piece_type = promote_to;
+            }
 
-            *self
-                .get_mut(m.to)
-                .ok_or_else(|| to_js_error("Invalid to position"))? = Some(piece);
+            self.put(m.to(), piece)?;
         }
 
         // Switch player
-        self.current_player = 3 - self.current_player;
+        self.switch_player();
+        self.move_count += 1;
 
+        // Saturates at the limit, which already decides the game; search
+        // stops at such positions, so unmake_move never sees a saturated move
+        if self.counts_checks()
+            && self.checks_given[(player - 1) as usize] < self.rules.check_limit
+            && is_in_check(self, self.current_player())
+        {
+            let count = &mut self.checks_given[(player - 1) as usize];
+            *count += 1;
+            self.hash ^= get_zobrist().check_key(player, *count);
+        }
+
+        self.debug_check("make_move");
         Ok(())
     }
 
-    /// Undo a move
-    pub fn unmake_move(&mut self, m: &Move, captured: Option<PieceType>) -> Result<(), JsValue> {
-        // Move piece back
-        if let Some(to_cell) = self.get_mut(m.to) {
-            let mut piece = to_cell
-                .take()
-                .ok_or_else(|| to_js_error("No piece at to position"))?;
+    /// Undo a move; `captured` is the piece that stood on `m.to()` before it
+    pub fn unmake_move(&mut self, m: &Move, captured: Option<Piece>) -> Result<(), JsValue> {
+        if self.counts_checks() && is_in_check(self, self.current_player()) {
+            let mover = 3 - self.current_player();
+            let count = &mut self.checks_given[(mover - 1) as usize];
+            self.hash ^= get_zobrist().check_key(mover, *count);
+            *count -= 1;
+        }
+
+        // Switch player back
+        self.switch_player();
+        self.move_count -= 1;
+        let player = self.current_player();
 
-            // Undo promotion
-            if m.promotion {
-                piece.promoted = m.promoted;
-            }
+        let mut piece = self
+            .take(m.to())?
+            .ok_or_else(|| to_js_error("No piece at to position"))?;
 
-            *self
-                .get_mut(m.from)
-                .ok_or_else(|| to_js_error("Invalid from position"))? = Some(piece);
+        if m.is_drop() {
+            self.add_to_hand(player, m.piece_type());
+            self.debug_check("unmake_move");
+            return Ok(());
         }
 
+        // Undo promotion
+        piece.piece_type = m.piece_type();
+        piece.promoted = m.was_promoted();
+        self.put(m.from(), piece)?;
+
         // Restore captured piece
-        if let Some(captured_type) = captured {
-            let opponent = 3 - self.current_player;
-            *self
-                .get_mut(m.to)
-                .ok_or_else(|| to_js_error("Invalid to position"))? = Some(Piece {
-                piece_type: captured_type,
-                player: opponent,
-                promoted: false,
-            });
+        if let Some(captured_piece) = captured {
+            self.put(m.to(), captured_piece)?;
 
             // Remove from hand
-            self.hands[(self.current_player - 1) as usize].retain(|&pt| pt != captured_type);
+            if self.keeps_in_hand(captured_piece.piece_type) {
+                self.remove_from_hand(player, captured_piece.piece_type)?;
+            }
         }
 
-        // Switch player back
-        self.current_player = 3 - self.current_player;
-
+        self.debug_check("unmake_move");
         Ok(())
     }
 }
@@ -1,3 +1,40 @@
+/// Search extensions, and how many plies they may add along one path
+#[derive(Clone, Copy, Debug)]
+pub struct Extensions {
+    pub check: bool,     // Positions in check
+    pub recapture: bool, // Captures back on the square just captured on
+    pub promotion: bool, // Promoting moves
+    pub singular: bool,  // TT moves much better than every alternative
+    pub budget: u8,
+}
+
+impl Extensions {
+    pub const NONE: Self = Self {
+        check: false,
+        recapture: false,
+        promotion: false,
+        singular: false,
+        budget: 0,
+    };
+
+    /// Check extensions only
+    pub const CHECKS: Self = Self {
+        check: true,
+        budget: 4,
+        ..Self::NONE
+    };
+
+    /// Check, recapture and singular extensions (promotion extensions are
+    /// left out: shogi promotes too often for them to pay)
+    pub const TACTICAL: Self = Self {
+        check: true,
+        recapture: true,
+        promotion: false,
+        singular: true,
+        budget: 4,
+    };
+}
+
 /// AI Configuration for different strength levels
 #[derive(Clone, Copy, Debug)]
 pub struct AIConfig {
@@ -9,6 +46,9 @@ pub struct AIConfig {
     pub use_killers: bool,
     pub qsearch_depth: u8,
     pub timeout_ms: u32, // Maximum thinking time in milliseconds
+    pub multipv: usize,  // Number of best lines searched at the root
+    pub extensions: Extensions,
+    pub forward_pruning: bool, // Futility, razoring, late-move and delta pruning
 }
 
 impl AIConfig {
@@ -24,6 +64,9 @@ impl AIConfig {
                 use_killers: false,
                 qsearch_depth: 2,
                 timeout_ms: 5000, // 5 seconds
+                multipv: 1,
+                extensions: Extensions::NONE,
+                forward_pruning: false,
             },
             2 => AIConfig {
                 level: 2,
@@ -34,6 +77,9 @@ impl AIConfig {
                 use_killers: false,
                 qsearch_depth: 3,
                 timeout_ms: 5000, // 5 seconds
+                multipv: 1,
+                extensions: Extensions::NONE,
+                forward_pruning: false,
             },
             3 => AIConfig {
                 level: 3,
@@ -44,6 +90,9 @@ impl AIConfig {
                 use_killers: false,
                 qsearch_depth: 4,
                 timeout_ms: 10000, // 10 seconds
+                multipv: 1,
+                extensions: Extensions::CHECKS,
+                forward_pruning: true,
             },
             4 => AIConfig {
                 level: 4,
@@ -54,6 +103,9 @@ impl AIConfig {
                 use_killers: true,
                 qsearch_depth: 4,
                 timeout_ms: 50000, // 15 seconds
+                multipv: 1,
+                extensions: Extensions::CHECKS,
+                forward_pruning: true,
             },
             5 => AIConfig {
                 level: 5,
@@ -64,6 +116,9 @@ impl AIConfig {
                 use_killers: true,
                 qsearch_depth: 5,
                 timeout_ms: 80000, // 20 seconds
+                multipv: 1,
+                extensions: Extensions::TACTICAL,
+                forward_pruning: true,
             },
             6 => AIConfig {
                 level: 6,
@@ -74,6 +129,9 @@ impl AIConfig {
                 use_killers: true,
                 qsearch_depth: 6,
                 timeout_ms: 12000, // 25 seconds
+                multipv: 1,
+                extensions: Extensions::TACTICAL,
+                forward_pruning: true,
             },
             _ => AIConfig::from_level(3), // Default to level 3
         }
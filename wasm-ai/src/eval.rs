@@ -1,108 +1,232 @@
 use crate::board::Board;
 use crate::config::AIConfig;
+use crate::moves::{landed_piece, royals};
 use crate::pst;
+use crate::rules::{RoyalSafety, RuleSet, WinCondition};
 use crate::types::*;
 
-/// Simple evaluation function
-/// Returns score from current player's perspective
-pub fn evaluate(board: &Board, config: &AIConfig) -> i32 {
-    let mut score = 0;
+/// Value of each royal beyond the first when only one has to survive
+const SPARE_ROYAL_VALUE: i32 = 1500;
 
-    // Material evaluation
-    for row in 0..board.size() {
-        for col in 0..board.size() {
-            if let Some(piece) = board.get(Position { row, col }) {
-                // Skip king evaluation (always 1 per side, doesn't affect relative score)
-                if piece.piece_type == PieceType::King || piece.piece_type == PieceType::ChessKing {
-                    continue;
-                }
+/// Exchange value of a royal piece, so capturing one comes first and
+/// recapturing with one into a defended square never pays off
+const ROYAL_VALUE: i32 = 20000;
 
-                let piece_value = get_piece_value(&piece.piece_type, piece.promoted);
-
-                // Add PST bonus if enabled
-                let pst_bonus = if config.use_pst {
-                    pst::get_pst_value(&piece.piece_type, row, col, piece.player)
-                } else {
-                    0
-                };
+/// Material and PST sums per player (index 0 = Player 1)
+///
+/// Kept on `Board` and updated by every piece placement and hand change, so
+/// the static evaluation does not have to walk the board.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct EvalTerms {
+    pub material: [i32; 2],
+    pub pst: [i32; 2],
+}
 
-                if piece.player == board.current_player {
-                    score += piece_value + pst_bonus;
-                } else {
-                    score -= piece_value + pst_bonus;
+impl EvalTerms {
+    /// Sums for a whole board, computed from scratch
+    pub fn compute(board: &Board) -> Self {
+        let mut terms = Self::default();
+        for row in 0..board.rows() {
+            for col in 0..board.cols() {
+                let pos = Position { row, col };
+                if let Some(piece) = board.get(pos) {
+                    terms.add_piece(&board.rules, piece, pos);
                 }
             }
         }
+        for player in [1, 2] {
+            for piece_type in PieceType::ALL {
+                for _ in 0..board.hand_count(player, piece_type) {
+                    terms.add_to_hand(&board.rules, player, piece_type);
+                }
+            }
+        }
+        terms
+    }
+
+    pub fn add_piece(&mut self, rules: &RuleSet, piece: &Piece, pos: Position) {
+        let (material, pst) = piece_terms(rules, piece, pos);
+        let player_idx = (piece.player - 1) as usize;
+        self.material[player_idx] += material;
+        self.pst[player_idx] += pst;
+    }
+
+    pub fn remove_piece(&mut self, rules: &RuleSet, piece: &Piece, pos: Position) {
+        let (material, pst) = piece_terms(rules, piece, pos);
+        let player_idx = (piece.player - 1) as usize;
+        self.material[player_idx] -= material;
+        self.pst[player_idx] -= pst;
+    }
+
+    pub fn add_to_hand(&mut self, rules: &RuleSet, player: Player, piece_type: PieceType) {
+        self.material[(player - 1) as usize] += hand_piece_value(rules, player, piece_type);
     }
 
-    // Hand pieces value
-    for &piece_type in &board.hands[(board.current_player - 1) as usize] {
-        score += get_piece_value(&piece_type, false) / 2;
+    pub fn remove_from_hand(&mut self, rules: &RuleSet, player: Player, piece_type: PieceType) {
+        self.material[(player - 1) as usize] -= hand_piece_value(rules, player, piece_type);
     }
+}
 
-    let opponent = 3 - board.current_player;
-    for &piece_type in &board.hands[(opponent - 1) as usize] {
-        score -= get_piece_value(&piece_type, false) / 2;
+/// Material and PST value of a piece on `pos` for its owner
+fn piece_terms(rules: &RuleSet, piece: &Piece, pos: Position) -> (i32, i32) {
+    // Skip royals (their loss ends the game rather than costing material)
+    if rules.is_royal(piece.piece_type) {
+        return (0, 0);
     }
 
+    let material = get_piece_value(rules.movement(piece));
+    let pst = pst::get_pst_value(
+        &piece.piece_type,
+        pos.row,
+        pos.col,
+        piece.player,
+        rules.rows,
+        rules.cols,
+    );
+    (material, pst)
+}
+
+/// Half the value of a piece in hand (only where the rules let it be dropped)
+fn hand_piece_value(rules: &RuleSet, player: Player, piece_type: PieceType) -> i32 {
+    if rules.has_hand(player) {
+        get_piece_value(piece_type.movement()) / 2
+    } else {
+        0
+    }
+}
+
+/// Material the side to move gains with capture `m` of `victim`: the
+/// victim's value on the board and in hand, plus any promotion
+pub fn capture_gain(board: &Board, m: Move, victim: &Piece) -> i32 {
+    let rules = &board.rules;
+    let player = board.current_player();
+    let mut gain = get_piece_value(rules.movement(victim))
+        + hand_piece_value(rules, player, victim.piece_type);
+
+    if m.is_promotion() || m.promote_to().is_some() {
+        let moving = Piece {
+            piece_type: m.piece_type(),
+            player,
+            promoted: m.was_promoted(),
+        };
+        gain += get_piece_value(rules.movement(&landed_piece(board, m)))
+            - get_piece_value(rules.movement(&moving));
+    }
+    gain
+}
+
+/// Simple evaluation function
+/// Returns score from current player's perspective
+pub fn evaluate(board: &Board, config: &AIConfig) -> i32 {
+    let us = (board.current_player() - 1) as usize;
+    let them = 1 - us;
+    let opponent = 3 - board.current_player();
+
+    // Material (board and hand) and piece placement
+    let terms = &board.terms;
+    let mut score = terms.material[us] - terms.material[them];
+    if config.use_pst {
+        score += terms.pst[us] - terms.pst[them];
+    }
+
+    // Spare royals are extra lives when only one has to survive
+    if board.rules.royal_safety == RoyalSafety::Any {
+        score += spare_royals(board, board.current_player()) - spare_royals(board, opponent);
+    }
+
+    // Progress towards alternative win conditions
+    score += goal_progress(board, board.current_player()) - goal_progress(board, opponent);
+
     score
 }
 
-fn get_piece_value(piece_type: &PieceType, promoted: bool) -> i32 {
-    match piece_type {
+fn spare_royals(board: &Board, player: Player) -> i32 {
+    royals(board, player).count().saturating_sub(1) as i32 * SPARE_ROYAL_VALUE
+}
+
+/// Bonus for getting closer to the variant's non-checkmate goals
+fn goal_progress(board: &Board, player: Player) -> i32 {
+    let rules = &board.rules;
+    let mut bonus = 0;
+
+    if rules.wins_by(WinCondition::ThreeCheck) {
+        bonus += board.checks_given[(player - 1) as usize] as i32 * 250;
+    }
+
+    let hill = rules.wins_by(WinCondition::KingOfTheHill);
+    let try_rule = rules.wins_by(WinCondition::Try);
+    if !hill && !try_rule {
+        return bonus;
+    }
+    let max_dist = board.rows().max(board.cols()) as i32;
+
+    // Measured from whichever royal is closest
+    if hill {
+        let dist = royals(board, player)
+            .flat_map(|king_pos| {
+                rules
+                    .hill_squares
+                    .iter()
+                    .map(move |&pos| distance(king_pos, pos))
+            })
+            .min()
+            .unwrap_or(max_dist);
+        bonus += (max_dist - dist) * 40;
+    }
+    if try_rule {
+        let target = rules.try_square(player);
+        let dist = royals(board, player)
+            .map(|king_pos| distance(king_pos, target))
+            .min()
+            .unwrap_or(max_dist);
+        bonus += (max_dist - dist) * 30;
+    }
+
+    bonus
+}
+
+/// King-move distance between two squares
+fn distance(a: Position, b: Position) -> i32 {
+    let dr = (a.row as i32 - b.row as i32).abs();
+    let dc = (a.col as i32 - b.col as i32).abs();
+    dr.max(dc)
+}
+
+/// Material value of a piece moving like `movement`
+///
+/// Promoted pieces are valued by what the promotion table makes them move as.
+/// Kings that are not royal under the variant are ordinary pieces; royals
+/// are left out of material altogether.
+pub fn get_piece_value(movement: Movement) -> i32 {
+    match movement {
         // Shogi pieces
-        PieceType::King => 100000,
-        PieceType::Rook => {
-            if promoted {
-                1000
-            } else {
-                900
-            }
-        }
-        PieceType::Bishop => {
-            if promoted {
-                850
-            } else {
-                750
-            }
-        }
-        PieceType::Gold => 600,
-        PieceType::Silver => {
-            if promoted {
-                600
-            } else {
-                500
-            }
-        }
-        PieceType::Knight => {
-            if promoted {
-                600
-            } else {
-                350
-            }
-        }
-        PieceType::Lance => {
-            if promoted {
-                600
-            } else {
-                300
-            }
-        }
-        PieceType::Pawn => {
-            if promoted {
-                600
-            } else {
-                100
-            }
-        }
+        Movement::King => 700,
+        Movement::Rook => 900,
+        Movement::Bishop => 750,
+        Movement::Gold => 600,
+        Movement::Silver => 500,
+        Movement::Knight => 350,
+        Movement::Lance => 300,
+        Movement::Pawn => 100,
+        Movement::Dragon => 1000,
+        Movement::Horse => 850,
 
         // Chess pieces
-        PieceType::ChessKing => 100000,
-        PieceType::ChessQueen => 950,
-        PieceType::ChessRook => 500,
-        PieceType::ChessBishop => 330,
-        PieceType::ChessKnight => 320,
-        PieceType::ChessPawn => 100,
+        Movement::ChessQueen => 950,
+        Movement::ChessRook => 500,
+        Movement::ChessBishop => 330,
+        Movement::ChessKnight => 320,
+        Movement::ChessPawn => 100,
+    }
+}
+
+/// Value of `piece` when it is captured, for move ordering and exchanges:
+/// its material value, or `ROYAL_VALUE` for royals
+pub fn exchange_value(rules: &RuleSet, piece: &Piece) -> i32 {
+    if rules.is_royal(piece.piece_type) {
+        ROYAL_VALUE
+    } else {
+        get_piece_value(rules.movement(piece))
     }
 }
 
@@ -112,13 +236,12 @@ pub fn is_under_attack(board: &Board, pos: Position, by_player: Player) -> bool
     // Simplified attack detection
     // For WASM version, we use a simpler heuristic
 
-    for row in 0..board.size() {
-        for col in 0..board.size() {
+    for row in 0..board.rows() {
+        for col in 0..board.cols() {
             if let Some(piece) = board.get(Position { row, col }) {
-                if piece.player == by_player {
-                    if can_attack(board, Position { row, col }, pos, piece) {
-                        return true;
-                    }
+                if piece.player == by_player && can_attack(board, Position { row, col }, pos, piece)
+                {
+                    return true;
                 }
             }
         }
@@ -0,0 +1,150 @@
+//! Statistics for ordering quiet moves: piece-to-square history, counter
+//! moves and continuation history
+//!
+//! Moves are keyed by whether they drop, the moving piece type and the
+//! target square. Continuation history pairs the move played one ply
+//! earlier with the reply, per player; its entries are `i8` so that
+//! 2 x keys x keys of them fit in about 10 MB on 9x9 and 23 MB on 11x11.
+//! Tables are sized from the board being searched and kept across searches.
+
+use crate::types::*;
+
+/// Magnitude no entry exceeds under gravity updates (fits in `i16`)
+const HISTORY_MAX: i32 = 16384;
+
+/// Largest single update, reached at depth 8
+const MAX_BONUS: i32 = 2048;
+
+/// Quiet-history value of one continuation unit (`i8::MAX` units reach
+/// about `HISTORY_MAX`)
+const CONTINUATION_SCALE: i32 = 128;
+
+pub struct History {
+    rows: usize,
+    cols: usize,
+    squares: usize,
+    keys: usize,                      // Drop or not, piece types and board squares
+    quiet: Vec<i16>,                  // [player][key]
+    counter_moves: Vec<Option<Move>>, // [player][key of the move answered]
+    continuation: Vec<i8>,            // [player][key one ply back][key]
+}
+
+impl History {
+    pub fn new(rows: usize, cols: usize) -> Self {
+        let squares = rows * cols;
+        let keys = 2 * NUM_PIECE_TYPES * squares;
+        Self {
+            rows,
+            cols,
+            squares,
+            keys,
+            quiet: vec![0; 2 * keys],
+            counter_moves: vec![None; 2 * keys],
+            continuation: vec![0; 2 * keys * keys],
+        }
+    }
+
+    /// Get ready to search a `rows` x `cols` board: statistics from a board
+    /// of another size are dropped, and older ones count half
+    pub fn new_search(&mut self, rows: usize, cols: usize) {
+        if (rows, cols) != (self.rows, self.cols) {
+            *self = Self::new(rows, cols);
+            return;
+        }
+        for entry in &mut self.quiet {
+            *entry /= 2;
+        }
+        for entry in &mut self.continuation {
+            *entry /= 2;
+        }
+    }
+
+    /// Forget everything, keeping the tables' size
+    pub fn clear(&mut self) {
+        *self = Self::new(self.rows, self.cols);
+    }
+
+    /// Heap memory held by the tables
+    pub fn memory_bytes(&self) -> usize {
+        use std::mem::size_of;
+        self.quiet.len() * size_of::<i16>()
+            + self.continuation.len() * size_of::<i8>()
+            + self.counter_moves.len() * size_of::<Option<Move>>()
+    }
+
+    fn to_index(&self, m: Move) -> usize {
+        let to = m.to();
+        to.row * self.cols + to.col
+    }
+
+    fn key(&self, m: Move) -> usize {
+        let piece = m.is_drop() as usize * NUM_PIECE_TYPES + m.piece_type().index();
+        piece * self.squares + self.to_index(m)
+    }
+
+    fn player_key(&self, player: Player, m: Move) -> usize {
+        (player as usize - 1) * self.keys + self.key(m)
+    }
+
+    /// Ordering score of quiet `m` by `player`, after the move played one
+    /// ply earlier
+    pub fn score(&self, player: Player, m: Move, previous: Option<Move>) -> i32 {
+        let key = self.key(m);
+        let mut score = self.quiet[self.player_key(player, m)] as i32;
+        if let Some(earlier) = previous {
+            let index = self.player_key(player, earlier) * self.keys + key;
+            score += self.continuation[index] as i32 * CONTINUATION_SCALE;
+        }
+        score
+    }
+
+    /// Quiet move by `player` that last refuted the opponent's `previous`
+    pub fn counter_move(&self, player: Player, previous: Option<Move>) -> Option<Move> {
+        self.counter_moves[self.player_key(player, previous?)]
+    }
+
+    /// Reward `best`, which caused a cutoff at `depth`, and punish the quiet
+    /// moves in `failed` that were searched before it
+    pub fn update(
+        &mut self,
+        player: Player,
+        best: Move,
+        best_is_quiet: bool,
+        failed: impl Iterator<Item = Move>,
+        previous: Option<Move>,
+        depth: u8,
+    ) {
+        let bonus = (32 * depth as i32 * depth as i32).min(MAX_BONUS);
+
+        if best_is_quiet {
+            self.add(player, best, previous, bonus);
+            if let Some(earlier) = previous {
+                let index = self.player_key(player, earlier);
+                self.counter_moves[index] = Some(best);
+            }
+        }
+        for m in failed {
+            self.add(player, m, previous, -bonus);
+        }
+    }
+
+    fn add(&mut self, player: Player, m: Move, previous: Option<Move>, bonus: i32) {
+        let index = self.player_key(player, m);
+        let entry = &mut self.quiet[index];
+        *entry = apply_gravity(*entry as i32, bonus, HISTORY_MAX) as i16;
+
+        if let Some(earlier) = previous {
+            let index = self.player_key(player, earlier) * self.keys + self.key(m);
+            // Even the smallest bonus moves an entry by one unit
+            let units = (bonus.abs() / CONTINUATION_SCALE).max(1) * bonus.signum();
+            let entry = &mut self.continuation[index];
+            *entry = apply_gravity(*entry as i32, units, i8::MAX as i32) as i8;
+        }
+    }
+}
+
+/// Move `value` towards `bonus`'s sign, by less the closer it already is to
+/// `max`, so entries saturate at `max` instead of overflowing
+fn apply_gravity(value: i32, bonus: i32, max: i32) -> i32 {
+    value + bonus - value * bonus.abs() / max
+}
@@ -1,13 +1,17 @@
-use js_sys;
 use wasm_bindgen::prelude::*;
-use web_sys;
 
+mod bench;
+mod bitboard;
 mod board;
 mod config;
 mod eval;
+mod history;
 mod moves;
+mod platform;
 mod pst;
+mod rules;
 mod search;
+mod see;
 mod tt;
 mod types;
 mod zobrist;
@@ -30,6 +34,11 @@ pub fn init() {
 #[wasm_bindgen]
 pub struct WasmAI {
     config: config::AIConfig,
+    rules: Option<rules::RuleSet>,
+    tt: tt::TranspositionTable, // Kept across moves until new_game/clear_hash
+    history: history::History,  // Move-ordering statistics, kept until new_game
+    hash_mb: Option<usize>,     // Set by set_hash_mb, overrides the level's size
+    on_info: Option<js_sys::Function>,
 }
 
 #[wasm_bindgen]
@@ -38,14 +47,18 @@ impl WasmAI {
     #[wasm_bindgen(constructor)]
     pub fn new(level: u8) -> Self {
         let config = config::AIConfig::from_level(level);
-        web_sys::console::log_1(
-            &format!(
-                "WASM AI initialized: Level={}, Depth={}, TT={}MB",
-                config.level, config.max_depth, config.tt_size_mb
-            )
-            .into(),
-        );
-        Self { config }
+        platform::log(&format!(
+            "WASM AI initialized: Level={}, Depth={}, TT={}MB",
+            config.level, config.max_depth, config.tt_size_mb
+        ));
+        Self {
+            config,
+            rules: None,
+            tt: tt::TranspositionTable::new(config.tt_size_mb),
+            history: history::History::new(0, 0),
+            hash_mb: None,
+            on_info: None,
+        }
     }
 
     /// Get the best move for the current board state
@@ -57,34 +70,55 @@ impl WasmAI {
     /// JSON string with the best move, or error
     #[wasm_bindgen]
     pub fn get_best_move(&mut self, board_json: &str) -> Result<String, JsValue> {
-        web_sys::console::log_1(
-            &format!(
-                "get_best_move called: Level={}, Depth={}",
-                self.config.level, self.config.max_depth
-            )
-            .into(),
-        );
-
-        let start = js_sys::Date::now();
+        platform::log(&format!(
+            "get_best_move called: Level={}, Depth={}",
+            self.config.level, self.config.max_depth
+        ));
 
-        let game_state: GameStateInput = serde_json::from_str(board_json)
-            .map_err(|e| JsValue::from_str(&format!("Failed to parse board: {}", e)))?;
+        let config = self.config;
+        let result = self.search(board_json, &config)?;
 
-        let board = board::Board::from_game_state(&game_state)?;
-        let best_move = search::find_best_move(&board, game_state.current_player, &self.config)?;
+        let output = SearchOutput {
+            best_move: MoveOutput::from_move(&result.best_move()),
+            line: line_output(&result.lines[0]),
+            depth: result.depth,
+        };
+        serde_json::to_string(&output)
+            .map_err(|e| JsValue::from_str(&format!("Failed to serialize move: {}", e)))
+    }
 
-        let elapsed = js_sys::Date::now() - start;
-        web_sys::console::log_1(&format!("Search completed in {:.2}ms", elapsed).into());
+    /// Get the `count` best moves for analysis
+    ///
+    /// Returns JSON with the depth reached and one line (score and PV) per
+    /// move, best first. There are fewer lines if there are fewer legal moves.
+    #[wasm_bindgen]
+    pub fn get_best_lines(&mut self, board_json: &str, count: usize) -> Result<String, JsValue> {
+        let config = config::AIConfig {
+            multipv: count.max(1),
+            ..self.config
+        };
+        let result = self.search(board_json, &config)?;
 
-        let move_output = MoveOutput::from_move(&best_move);
-        serde_json::to_string(&move_output)
-            .map_err(|e| JsValue::from_str(&format!("Failed to serialize move: {}", e)))
+        let output = AnalysisOutput {
+            depth: result.depth,
+            lines: result.lines.iter().map(line_output).collect(),
+        };
+        serde_json::to_string(&output)
+            .map_err(|e| JsValue::from_str(&format!("Failed to serialize lines: {}", e)))
     }
 
     /// Set the AI strength level (1-6)
     #[wasm_bindgen]
     pub fn set_level(&mut self, level: u8) {
         self.config = config::AIConfig::from_level(level);
+        if let Some(hash_mb) = self.hash_mb {
+            self.config.tt_size_mb = hash_mb;
+        }
+        if self.tt.size_mb() != self.config.tt_size_mb {
+            if let Err(e) = self.tt.resize(self.config.tt_size_mb) {
+                platform::log(&e);
+            }
+        }
     }
 
     /// Get current strength level
@@ -96,7 +130,7 @@ impl WasmAI {
     /// Set custom depth (overrides level)
     #[wasm_bindgen]
     pub fn set_depth(&mut self, depth: u8) {
-        self.config.max_depth = depth.max(1).min(8);
+        self.config.max_depth = depth.clamp(1, 8);
     }
 
     /// Get current search depth
@@ -104,4 +138,187 @@ impl WasmAI {
     pub fn get_depth(&self) -> u8 {
         self.config.max_depth
     }
+
+    /// Set the variant rules used when the board JSON has no `rules` field
+    ///
+    /// # Arguments
+    /// * `rules_json` - JSON string with the rule set (missing fields use defaults)
+    #[wasm_bindgen]
+    pub fn set_rules(&mut self, rules_json: &str) -> Result<(), JsValue> {
+        let rules: rules::RuleSet = serde_json::from_str(rules_json)
+            .map_err(|e| JsValue::from_str(&format!("Failed to parse rules: {}", e)))?;
+        self.rules = Some(rules);
+        self.tt.clear();
+        self.history.clear();
+        Ok(())
+    }
+
+    /// Go back to the default rules
+    #[wasm_bindgen]
+    pub fn clear_rules(&mut self) {
+        self.rules = None;
+        self.tt.clear();
+        self.history.clear();
+    }
+
+    /// Forget everything learnt in the previous game
+    #[wasm_bindgen]
+    pub fn new_game(&mut self) {
+        self.tt.clear();
+        self.history.clear();
+    }
+
+    /// Empty the transposition table
+    #[wasm_bindgen]
+    pub fn clear_hash(&mut self) {
+        self.tt.clear();
+    }
+
+    /// Set the transposition table size in MB (levels 1-2 do not use it)
+    ///
+    /// The new table is allocated before the old one is dropped, so both
+    /// must fit at once. Fails without changing the table if the size is
+    /// over the limit or the memory cannot be allocated.
+    #[wasm_bindgen]
+    pub fn set_hash_mb(&mut self, mb: usize) -> Result<(), JsValue> {
+        self.tt.resize(mb).map_err(|e| to_js_error(&e))?;
+        self.config.tt_size_mb = mb;
+        self.hash_mb = Some(mb);
+        Ok(())
+    }
+
+    /// Current transposition table size in MB
+    #[wasm_bindgen]
+    pub fn get_hash_mb(&self) -> usize {
+        self.tt.size_mb()
+    }
+
+    /// Estimated memory used by the search in bytes (table, move-ordering
+    /// history and search buffers)
+    #[wasm_bindgen]
+    pub fn memory_estimate(&self) -> usize {
+        self.tt.memory_bytes() + self.history.memory_bytes() + search::state_memory_bytes()
+    }
+
+    /// Export the transposition table as bytes (a `Uint8Array` in JS)
+    #[wasm_bindgen]
+    pub fn export_hash(&self) -> Vec<u8> {
+        self.tt.export()
+    }
+
+    /// Load a table saved by `export_hash`, taking over its size
+    ///
+    /// Fails without changing the table if the data is damaged or was saved
+    /// by an incompatible version or with different keys. Entries saved
+    /// under other rules or another board size are dropped by the first
+    /// search that does not match them.
+    #[wasm_bindgen]
+    pub fn import_hash(&mut self, data: &[u8]) -> Result<(), JsValue> {
+        self.tt.import(data).map_err(|e| to_js_error(&e))?;
+        self.config.tt_size_mb = self.tt.size_mb();
+        self.hash_mb = Some(self.tt.size_mb());
+        Ok(())
+    }
+
+    /// Register a function called with search progress as a JSON string
+    /// (after every iteration and about twice a second); `null` removes it
+    #[wasm_bindgen]
+    pub fn set_info_callback(&mut self, callback: Option<js_sys::Function>) {
+        self.on_info = callback;
+    }
+
+    /// How full the transposition table is, in permille of entries written
+    /// by the last search
+    #[wasm_bindgen]
+    pub fn hashfull(&self) -> u32 {
+        self.tt.hashfull()
+    }
+}
+
+impl WasmAI {
+    /// Parse `board_json` and search it with `config`
+    fn search(
+        &mut self,
+        board_json: &str,
+        config: &config::AIConfig,
+    ) -> Result<search::SearchResult, JsValue> {
+        let start = platform::now_ms();
+
+        let game_state: GameStateInput = serde_json::from_str(board_json)
+            .map_err(|e| JsValue::from_str(&format!("Failed to parse board: {}", e)))?;
+
+        let board = board::Board::from_game_state(&game_state, self.rules.as_ref())?;
+        let on_info = &self.on_info;
+        let result = search::find_best_move(
+            &board,
+            game_state.current_player,
+            config,
+            &mut self.tt,
+            &mut self.history,
+            &mut |info| {
+                if let Some(callback) = on_info {
+                    let json = serde_json::to_string(&info_output(info)).unwrap_or_default();
+                    // A throwing callback must not abort the search
+                    let _ = callback.call1(&JsValue::NULL, &JsValue::from_str(&json));
+                }
+            },
+        )?;
+
+        let elapsed = platform::now_ms() - start;
+        platform::log(&format!("Search completed in {:.2}ms", elapsed));
+        Ok(result)
+    }
+}
+
+/// Search a fixed set of positions to `depth` and report nodes per second
+#[wasm_bindgen]
+pub fn bench(depth: u8) -> Result<String, JsValue> {
+    let results = bench::run(depth.clamp(1, 8))?;
+
+    let mut report = String::new();
+    let (mut total_nodes, mut total_ms) = (0, 0.0);
+    for result in &results {
+        report += &format!(
+            "{:<8} nodes={:<10} time={:.0}ms nps={:.0}\n",
+            result.name,
+            result.nodes,
+            result.time_ms,
+            nps(result.nodes, result.time_ms)
+        );
+        total_nodes += result.nodes;
+        total_ms += result.time_ms;
+    }
+    report += &format!(
+        "total    nodes={:<10} time={:.0}ms nps={:.0}",
+        total_nodes,
+        total_ms,
+        nps(total_nodes, total_ms)
+    );
+    Ok(report)
+}
+
+fn line_output(line: &search::SearchLine) -> LineOutput {
+    LineOutput {
+        score: search::display_score(line.score),
+        pv: line.pv.iter().map(MoveOutput::from_move).collect(),
+    }
+}
+
+fn info_output(info: &search::SearchInfo) -> InfoOutput {
+    InfoOutput {
+        multipv: info.multipv,
+        depth: info.depth,
+        seldepth: info.seldepth,
+        score: search::display_score(info.score),
+        bound: info.bound,
+        nodes: info.nodes,
+        nps: nps(info.nodes, info.time_ms) as u64,
+        time_ms: info.time_ms as u64,
+        hashfull: info.hashfull,
+        pv: info.pv.iter().map(MoveOutput::from_move).collect(),
+    }
+}
+
+fn nps(nodes: usize, time_ms: f64) -> f64 {
+    nodes as f64 * 1000.0 / time_ms.max(1.0)
 }
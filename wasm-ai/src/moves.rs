@@ -1,58 +1,108 @@
+use crate::bitboard::{position, square, Bitboard, Square};
 use crate::board::Board;
+use crate::rules::{RoyalSafety, WinCondition};
 use crate::types::*;
 
-/// Find the king position for a given player
-fn find_king(board: &Board, player: Player) -> Option<Position> {
-    for row in 0..board.size() {
-        for col in 0..board.size() {
-            if let Some(piece) = board.get(Position { row, col }) {
-                if piece.player == player
-                    && (piece.piece_type == PieceType::King
-                        || piece.piece_type == PieceType::ChessKing)
-                {
-                    return Some(Position { row, col });
-                }
-            }
-        }
-    }
-    None
+/// Positions of all royal pieces of a player
+pub fn royals(board: &Board, player: Player) -> impl Iterator<Item = Position> {
+    board
+        .bits
+        .royals(player, &board.rules)
+        .squares()
+        .map(position)
 }
 
-/// Check if a piece at `from` can attack position `to`
-fn can_attack(board: &Board, from: Position, to: Position, piece: &Piece) -> bool {
-    let mut attack_moves = Vec::new();
-    generate_piece_moves(board, from, piece, &mut attack_moves);
-    attack_moves.iter().any(|m| m.to == to)
+/// Check if any piece of `by_player` attacks `pos`
+fn is_attacked(board: &Board, pos: Position, by_player: Player) -> bool {
+    board.bits.is_attacked(square(pos), by_player, &board.rules)
 }
 
-/// Check if a player's king is in check
+/// Check if a player's royals are in check
+///
+/// With `royalSafety: "all"` one attacked royal is check; with `"any"` the
+/// player is only in check once every royal is attacked. A player without
+/// royals is never in check.
 pub fn is_in_check(board: &Board, player: Player) -> bool {
-    // Find the king position
-    let king_pos = match find_king(board, player) {
-        Some(pos) => pos,
-        None => return false, // No king found (already captured)
+    let opponent = 3 - player;
+    let mut royal_positions = royals(board, player).peekable();
+    if royal_positions.peek().is_none() {
+        return false;
+    }
+
+    match board.rules.royal_safety {
+        RoyalSafety::All => royal_positions.any(|pos| is_attacked(board, pos, opponent)),
+        RoyalSafety::Any => royal_positions.all(|pos| is_attacked(board, pos, opponent)),
+    }
+}
+
+/// The piece standing on the target square once `m` is played
+pub fn landed_piece(board: &Board, m: Move) -> Piece {
+    let mut piece = Piece {
+        piece_type: m.piece_type(),
+        player: board.current_player(),
+        promoted: m.was_promoted(),
     };
+    if m.is_promotion() {
+        piece.promoted = true;
+    }
+    if let Some(piece_type) = m.promote_to() {
+        piece.piece_type = piece_type;
+    }
+    piece
+}
 
-    // Check if any opponent piece can attack the king
-    let opponent = if player == 1 { 2 } else { 1 };
-
-    for row in 0..board.size() {
-        for col in 0..board.size() {
-            if let Some(piece) = board.get(Position { row, col }) {
-                if piece.player == opponent {
-                    if can_attack(board, Position { row, col }, king_pos, piece) {
-                        return true;
-                    }
-                }
-            }
-        }
+/// Cheap test of whether a non-capturing `m` attacks an enemy royal,
+/// directly or by uncovering a slider
+///
+/// Never misses a check, but with `royalSafety: "any"` it also accepts moves
+/// that attack only some of the royals; confirm with `is_in_check`.
+pub fn may_give_check(board: &Board, m: Move) -> bool {
+    let bits = &board.bits;
+    let player = board.current_player();
+    let to = m.target_square();
+
+    let mut occupied = bits.occupied();
+    occupied.toggle(to);
+    if !m.is_drop() {
+        occupied.toggle(m.origin_square());
     }
 
-    false
+    let movement = board.rules.movement(&landed_piece(board, m));
+    let royals = bits.royals(3 - player, &board.rules);
+    !(bits.attacks(to, movement, player, occupied) & royals).is_empty()
+        || royals.squares().any(|sq| {
+            !bits
+                .attackers_with(sq, player, &board.rules, occupied)
+                .is_empty()
+        })
+}
+
+/// Whether a player has lost the royals the rules require it to keep
+fn has_lost_royals(board: &Board, player: Player) -> bool {
+    let at_start = board.royals_at_start[(player - 1) as usize] as u32;
+    if at_start == 0 {
+        return false;
+    }
+
+    let remaining = board.bits.royals(player, &board.rules).count();
+    match board.rules.royal_safety {
+        RoyalSafety::All => remaining < at_start,
+        RoyalSafety::Any => remaining == 0,
+    }
+}
+
+/// Whether a player has no pieces left on the board or in hand
+fn has_no_pieces(board: &Board, player: Player) -> bool {
+    board.hand(player).iter().all(|&n| n == 0)
+        && board.bits.players[(player - 1) as usize].is_empty()
 }
 
 /// Check if a move is legal (doesn't put own king in check)
 fn is_legal_move(board: &Board, mv: &Move) -> bool {
+    if !board.rules.check_legality() {
+        return true;
+    }
+
     // Apply move temporarily
     let mut test_board = board.clone();
     if test_board.make_move(mv).is_err() {
@@ -61,332 +111,297 @@ fn is_legal_move(board: &Board, mv: &Move) -> bool {
 
     // Check if our king is in check after the move
     // Note: current_player has switched after make_move, so we check the previous player
-    let player_after_move = 3 - test_board.current_player;
+    let player_after_move = 3 - test_board.current_player();
     !is_in_check(&test_board, player_after_move)
 }
 
+/// Player who has reached a win condition other than checkmate, if any
+pub fn goal_winner(board: &Board) -> Option<Player> {
+    let rules = &board.rules;
+
+    for player in [1, 2] {
+        let opponent = 3 - player;
+
+        if rules.wins_by(WinCondition::KingCapture) && has_lost_royals(board, opponent) {
+            return Some(player);
+        }
+
+        if rules.wins_by(WinCondition::CaptureAll) && has_no_pieces(board, opponent) {
+            return Some(player);
+        }
+
+        if rules.wins_by(WinCondition::ThreeCheck)
+            && board.checks_given[(player - 1) as usize] >= rules.check_limit
+        {
+            return Some(player);
+        }
+
+        if rules.wins_by(WinCondition::KingOfTheHill)
+            && royals(board, player).any(|pos| rules.is_hill_square(pos))
+        {
+            return Some(player);
+        }
+        if rules.wins_by(WinCondition::Try)
+            && royals(board, player).any(|pos| pos == rules.try_square(player))
+        {
+            return Some(player);
+        }
+    }
+
+    None
+}
+
 /// Generate all legal moves for the current player
 pub fn generate_moves(board: &Board) -> Vec<Move> {
     let mut moves = Vec::new();
+    generate_moves_into(board, &mut moves);
+    moves
+}
+
+/// Generate all legal moves into `moves`, reusing its capacity
+pub fn generate_moves_into(board: &Board, moves: &mut Vec<Move>) {
+    moves.clear();
 
     // Generate moves for pieces on board
-    for row in 0..board.size() {
-        for col in 0..board.size() {
-            if let Some(piece) = board.get(Position { row, col }) {
-                if piece.player == board.current_player {
-                    generate_piece_moves(board, Position { row, col }, piece, &mut moves);
-                }
-            }
+    let own = board.bits.players[(board.current_player() - 1) as usize];
+    for from in own.squares() {
+        if let Some(piece) = board.get(position(from)) {
+            generate_piece_moves(board, from, piece, moves);
         }
     }
 
-    // TODO: Add drop moves for pieces in hand
-    // For now, focusing on basic piece movement
+    generate_drops(board, moves);
 
     // Filter out illegal moves (that would put king in check)
-    moves
-        .into_iter()
-        .filter(|mv| is_legal_move(board, mv))
-        .collect()
+    moves.retain(|mv| {
+        is_legal_move(board, mv)
+            && !(mv.is_drop()
+                && mv.piece_type() == PieceType::Pawn
+                && board.rules.hands.no_pawn_drop_mate
+                && is_pawn_drop_mate(board, mv))
+    });
 }
 
-fn generate_piece_moves(board: &Board, from: Position, piece: &Piece, moves: &mut Vec<Move>) {
-    match piece.piece_type {
-        PieceType::King | PieceType::ChessKing => generate_king_moves(board, from, piece, moves),
-        PieceType::Rook => generate_rook_moves(board, from, piece, moves),
-        PieceType::Bishop => generate_bishop_moves(board, from, piece, moves),
-        PieceType::ChessQueen => generate_queen_moves(board, from, piece, moves),
-        PieceType::ChessRook => generate_chess_rook_moves(board, from, piece, moves),
-        PieceType::ChessBishop => generate_chess_bishop_moves(board, from, piece, moves),
-        PieceType::Knight | PieceType::ChessKnight => {
-            generate_knight_moves(board, from, piece, moves)
-        }
-        PieceType::Lance => generate_lance_moves(board, from, piece, moves),
-        PieceType::Pawn | PieceType::ChessPawn => generate_pawn_moves(board, from, piece, moves),
-        PieceType::Gold => generate_gold_moves(board, from, piece, moves),
-        PieceType::Silver => generate_silver_moves(board, from, piece, moves),
+fn generate_piece_moves(board: &Board, from: Square, piece: &Piece, moves: &mut Vec<Move>) {
+    for to in piece_targets(board, from, piece).squares() {
+        add_move(board, moves, position(from), position(to), piece);
     }
 }
 
-fn generate_king_moves(board: &Board, from: Position, piece: &Piece, moves: &mut Vec<Move>) {
-    let directions = [
-        (-1, -1),
-        (-1, 0),
-        (-1, 1),
-        (0, -1),
-        (0, 1),
-        (1, -1),
-        (1, 0),
-        (1, 1),
-    ];
-
-    for &(dr, dc) in &directions {
-        if let Some(to) = add_delta(from, dr, dc, board.size()) {
-            if can_move_to(board, to, piece.player) {
-                add_move(moves, from, to, piece, false);
-            }
+/// Squares a piece on the board can move to, ignoring legality
+fn piece_targets(board: &Board, from: Square, piece: &Piece) -> Bitboard {
+    let bits = &board.bits;
+    match board.rules.movement(piece) {
+        Movement::ChessPawn => chess_pawn_targets(board, from, piece),
+        movement => {
+            bits.attacks(from, movement, piece.player, bits.occupied())
+                & !bits.players[(piece.player - 1) as usize]
         }
     }
 }
 
-fn generate_rook_moves(board: &Board, from: Position, piece: &Piece, moves: &mut Vec<Move>) {
-    let directions = [(0, 1), (0, -1), (1, 0), (-1, 0)];
-
-    if piece.promoted {
-        // Promoted rook can also move diagonally by 1
-        generate_sliding_moves(board, from, piece, &directions, moves);
-        let diag_dirs = [(-1, -1), (-1, 1), (1, -1), (1, 1)];
-        for &(dr, dc) in &diag_dirs {
-            if let Some(to) = add_delta(from, dr, dc, board.size()) {
-                if can_move_to(board, to, piece.player) {
-                    add_move(moves, from, to, piece, false);
-                }
-            }
+/// Squares a chess pawn can move to: diagonal captures and pushes
+fn chess_pawn_targets(board: &Board, from: Square, piece: &Piece) -> Bitboard {
+    let bits = &board.bits;
+    let occupied = bits.occupied();
+    let enemies = bits.players[(2 - piece.player) as usize];
+
+    // Forward move (only if no piece ahead)
+    let push = bits.attacks(from, Movement::Pawn, piece.player, occupied) & !occupied;
+    // Can only capture enemy pieces diagonally
+    let mut targets =
+        push | bits.attacks(from, Movement::ChessPawn, piece.player, occupied) & enemies;
+
+    // Initial two-square move (real chess pawns only)
+    let start_row = board.rules.pawn_double_step_rows[(piece.player - 1) as usize];
+    if piece.piece_type == PieceType::ChessPawn
+        && !piece.promoted
+        && start_row == Some(position(from).row)
+    {
+        for middle in push.squares() {
+            targets |= bits.attacks(middle, Movement::Pawn, piece.player, occupied) & !occupied;
         }
-    } else {
-        generate_sliding_moves(board, from, piece, &directions, moves);
     }
+    targets
 }
 
-fn generate_bishop_moves(board: &Board, from: Position, piece: &Piece, moves: &mut Vec<Move>) {
-    let directions = [(-1, -1), (-1, 1), (1, -1), (1, 1)];
-
-    if piece.promoted {
-        // Promoted bishop can also move orthogonally by 1
-        generate_sliding_moves(board, from, piece, &directions, moves);
-        let orth_dirs = [(0, 1), (0, -1), (1, 0), (-1, 0)];
-        for &(dr, dc) in &orth_dirs {
-            if let Some(to) = add_delta(from, dr, dc, board.size()) {
-                if can_move_to(board, to, piece.player) {
-                    add_move(moves, from, to, piece, false);
-                }
-            }
+/// Add a board move, expanding it into the promotion choices the rules allow
+fn add_move(board: &Board, moves: &mut Vec<Move>, from: Position, to: Position, piece: &Piece) {
+    let rules = &board.rules;
+    let base = Move::new(from, to, piece);
+
+    let in_zone = rules.in_promotion_zone(piece.piece_type, piece.player, from.row)
+        || rules.in_promotion_zone(piece.piece_type, piece.player, to.row);
+    let must_promote = rules.is_dead_square(piece.piece_type, piece.player, to.row);
+
+    if piece.piece_type == PieceType::ChessPawn
+        && !piece.promoted
+        && in_zone
+        && !rules.promotion.chess_pawn.is_empty()
+    {
+        for &target in &rules.promotion.chess_pawn {
+            moves.push(base.with_promote_to(target));
+        }
+        if !must_promote {
+            moves.push(base);
+        }
+    } else if !piece.promoted && in_zone && rules.promotes(piece.piece_type) {
+        moves.push(base.with_promotion());
+        if !must_promote {
+            moves.push(base);
         }
     } else {
-        generate_sliding_moves(board, from, piece, &directions, moves);
+        moves.push(base);
     }
 }
 
-fn generate_queen_moves(board: &Board, from: Position, piece: &Piece, moves: &mut Vec<Move>) {
-    let directions = [
-        (0, 1),
-        (0, -1),
-        (1, 0),
-        (-1, 0),
-        (-1, -1),
-        (-1, 1),
-        (1, -1),
-        (1, 1),
-    ];
-    generate_sliding_moves(board, from, piece, &directions, moves);
-}
-
-fn generate_chess_rook_moves(board: &Board, from: Position, piece: &Piece, moves: &mut Vec<Move>) {
-    let directions = [(0, 1), (0, -1), (1, 0), (-1, 0)];
-    generate_sliding_moves(board, from, piece, &directions, moves);
-}
-
-fn generate_chess_bishop_moves(
-    board: &Board,
-    from: Position,
-    piece: &Piece,
-    moves: &mut Vec<Move>,
-) {
-    let directions = [(-1, -1), (-1, 1), (1, -1), (1, 1)];
-    generate_sliding_moves(board, from, piece, &directions, moves);
-}
+/// Generate drops of hand pieces onto empty squares
+fn generate_drops(board: &Board, moves: &mut Vec<Move>) {
+    let player = board.current_player();
+    if !board.rules.has_hand(player) {
+        return;
+    }
 
-fn generate_knight_moves(board: &Board, from: Position, piece: &Piece, moves: &mut Vec<Move>) {
-    let deltas = if piece.piece_type == PieceType::ChessKnight {
-        // Chess knight moves in all directions
-        vec![
-            (-2, -1),
-            (-2, 1),
-            (-1, -2),
-            (-1, 2),
-            (1, -2),
-            (1, 2),
-            (2, -1),
-            (2, 1),
-        ]
-    } else {
-        // Shogi knight (moves forward only)
-        if piece.promoted {
-            generate_gold_moves(board, from, piece, moves);
-            return;
+    for piece_type in PieceType::ALL {
+        if board.hand_count(player, piece_type) == 0 {
+            continue;
         }
-        let forward = if piece.player == 1 { -1 } else { 1 };
-        vec![(forward * 2, -1), (forward * 2, 1)]
-    };
 
-    for &(dr, dc) in &deltas {
-        if let Some(to) = add_delta(from, dr, dc, board.size()) {
-            if can_move_to(board, to, piece.player) {
-                add_move(moves, from, to, piece, false);
+        for to in drop_targets(board, player, piece_type).squares() {
+            let to = position(to);
+            if !board.rules.is_dead_square(piece_type, player, to.row) {
+                moves.push(Move::drop(piece_type, to));
             }
         }
     }
 }
 
-fn generate_lance_moves(board: &Board, from: Position, piece: &Piece, moves: &mut Vec<Move>) {
-    if piece.promoted {
-        generate_gold_moves(board, from, piece, moves);
-        return;
+/// Empty squares a hand piece may be dropped on, before dead squares
+fn drop_targets(board: &Board, player: Player, piece_type: PieceType) -> Bitboard {
+    let bits = &board.bits;
+    let mut targets = bits.on_board & !bits.occupied();
+    if board.rules.hands.nifu && piece_type == PieceType::Pawn {
+        let pawns = bits.pieces[PieceType::Pawn.index()]
+            & bits.players[(player - 1) as usize]
+            & !bits.promoted;
+        for pawn in pawns.squares() {
+            targets &= !bits.file(position(pawn).col);
+        }
     }
-    let forward = if piece.player == 1 { -1 } else { 1 };
-    generate_sliding_moves(board, from, piece, &[(forward, 0)], moves);
+    targets
 }
 
-fn generate_pawn_moves(board: &Board, from: Position, piece: &Piece, moves: &mut Vec<Move>) {
-    let forward = if piece.player == 1 { -1 } else { 1 };
-
-    if piece.piece_type == PieceType::Pawn {
-        // Shogi Pawn
-        if piece.promoted {
-            generate_gold_moves(board, from, piece, moves);
-            return;
-        }
-        // Move forward (can capture)
-        if let Some(to) = add_delta(from, forward, 0, board.size()) {
-            if can_move_to(board, to, piece.player) {
-                add_move(moves, from, to, piece, false);
-            }
-        }
-    } else {
-        // Chess Pawn: Forward move (only if no piece ahead)
-        if let Some(to) = add_delta(from, forward, 0, board.size()) {
-            if board.get(to).is_none() {
-                add_move(moves, from, to, piece, false);
+/// Whether the side to move has any legal move
+///
+/// Plays candidates on `board` and takes them back, stopping at the first
+/// legal one, so unlike `generate_moves` it never allocates. Promotion
+/// choices are not expanded: they leave the same squares occupied, so
+/// they cannot differ in whether they expose the mover's royals.
+pub fn has_legal_move(board: &mut Board) -> bool {
+    let player = board.current_player();
+    let own = board.bits.players[(player - 1) as usize];
+    for from in own.squares() {
+        let Some(piece) = board.get(position(from)).copied() else {
+            continue;
+        };
+        for to in piece_targets(board, from, &piece).squares() {
+            let mv = Move::new(position(from), position(to), &piece);
+            if is_legal_in_place(board, &mv) {
+                return true;
             }
         }
+    }
 
-        // Chess pawn diagonal captures
-        if piece.piece_type == PieceType::ChessPawn {
-            // Diagonal captures (left and right)
-            for &dc in &[-1, 1] {
-                if let Some(to) = add_delta(from, forward, dc, board.size()) {
-                    // Can only capture enemy pieces diagonally
-                    if let Some(target_piece) = board.get(to) {
-                        if target_piece.player != piece.player {
-                            add_move(moves, from, to, piece, false);
-                        }
-                    }
-                }
+    if !board.rules.has_hand(player) {
+        return false;
+    }
+    for piece_type in PieceType::ALL {
+        if board.hand_count(player, piece_type) == 0 {
+            continue;
+        }
+        for to in drop_targets(board, player, piece_type).squares() {
+            let to = position(to);
+            if board.rules.is_dead_square(piece_type, player, to.row) {
+                continue;
             }
-
-            // Initial two-square move for chess pawns
-            let start_row = if piece.player == 1 { 6 } else { 1 };
-            if from.row == start_row {
-                if let Some(middle) = add_delta(from, forward, 0, board.size()) {
-                    if board.get(middle).is_none() {
-                        if let Some(to) = add_delta(from, forward * 2, 0, board.size()) {
-                            if board.get(to).is_none() {
-                                add_move(moves, from, to, piece, false);
-                            }
-                        }
-                    }
-                }
+            let mv = Move::drop(piece_type, to);
+            if is_legal_in_place(board, &mv)
+                && !(piece_type == PieceType::Pawn
+                    && board.rules.hands.no_pawn_drop_mate
+                    && is_pawn_drop_mate(board, &mv))
+            {
+                return true;
             }
         }
     }
+    false
 }
 
-fn generate_gold_moves(board: &Board, from: Position, piece: &Piece, moves: &mut Vec<Move>) {
-    let forward = if piece.player == 1 { -1 } else { 1 };
-    let directions = [
-        (forward, -1),
-        (forward, 0),
-        (forward, 1),
-        (0, -1),
-        (0, 1),
-        (-forward, 0),
-    ];
-
-    for &(dr, dc) in &directions {
-        if let Some(to) = add_delta(from, dr, dc, board.size()) {
-            if can_move_to(board, to, piece.player) {
-                add_move(moves, from, to, piece, false);
-            }
-        }
+/// `is_legal_move` via make/unmake on `board` instead of a copy
+fn is_legal_in_place(board: &mut Board, mv: &Move) -> bool {
+    if !board.rules.check_legality() {
+        return true;
     }
-}
-
-fn generate_silver_moves(board: &Board, from: Position, piece: &Piece, moves: &mut Vec<Move>) {
-    if piece.promoted {
-        generate_gold_moves(board, from, piece, moves);
-        return;
+    // unmake_move cannot undo a move made at a saturated check count
+    if board.check_limit_reached(board.current_player()) {
+        return is_legal_move(board, mv);
     }
 
-    let forward = if piece.player == 1 { -1 } else { 1 };
-    let directions = [
-        (forward, -1),
-        (forward, 0),
-        (forward, 1),
-        (-forward, -1),
-        (-forward, 1),
-    ];
-
-    for &(dr, dc) in &directions {
-        if let Some(to) = add_delta(from, dr, dc, board.size()) {
-            if can_move_to(board, to, piece.player) {
-                add_move(moves, from, to, piece, false);
-            }
-        }
+    let captured = board.get(mv.to()).copied();
+    if board.make_move(mv).is_err() {
+        return false;
     }
+    let legal = !is_in_check(board, 3 - board.current_player());
+    board.unmake_move(mv, captured).ok();
+    legal
 }
 
-fn generate_sliding_moves(
-    board: &Board,
-    from: Position,
-    piece: &Piece,
-    directions: &[(i32, i32)],
-    moves: &mut Vec<Move>,
-) {
-    for &(dr, dc) in directions {
-        let mut current = from;
-        loop {
-            let Some(next) = add_delta(current, dr, dc, board.size()) else {
-                break;
-            };
-
-            if let Some(target_piece) = board.get(next) {
-                if target_piece.player != piece.player {
-                    add_move(moves, from, next, piece, false);
-                }
-                break;
-            }
-
-            add_move(moves, from, next, piece, false);
-            current = next;
-        }
+/// Whether a pawn drop gives checkmate (forbidden by `noPawnDropMate`)
+fn is_pawn_drop_mate(board: &Board, mv: &Move) -> bool {
+    let mut test_board = board.clone();
+    if test_board.make_move(mv).is_err() {
+        return false;
     }
+    let opponent = test_board.current_player();
+    is_in_check(&test_board, opponent) && !has_legal_move(&mut test_board)
 }
 
-fn add_delta(pos: Position, dr: i32, dc: i32, board_size: usize) -> Option<Position> {
-    let new_row = pos.row as i32 + dr;
-    let new_col = pos.col as i32 + dc;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bench;
 
-    if new_row >= 0 && new_row < board_size as i32 && new_col >= 0 && new_col < board_size as i32 {
-        Some(Position {
-            row: new_row as usize,
-            col: new_col as usize,
-        })
-    } else {
-        None
+    /// Leaf nodes of the legal move tree `depth` plies deep
+    fn perft(board: &Board, depth: u8) -> usize {
+        let moves = generate_moves(board);
+        if depth == 1 {
+            return moves.len();
+        }
+        moves
+            .iter()
+            .map(|m| {
+                let mut child = board.clone();
+                child.make_move(m).unwrap();
+                perft(&child, depth - 1)
+            })
+            .sum()
     }
-}
 
-fn can_move_to(board: &Board, pos: Position, player: Player) -> bool {
-    board.get(pos).map_or(true, |p| p.player != player)
-}
+    #[test]
+    fn perft_shogi_start() {
+        let board = bench::position("shogi");
+        assert_eq!(
+            [1, 2, 3, 4].map(|depth| perft(&board, depth)),
+            [30, 900, 25470, 719731]
+        );
+    }
 
-fn add_move(moves: &mut Vec<Move>, from: Position, to: Position, piece: &Piece, promotion: bool) {
-    moves.push(Move {
-        from,
-        to,
-        piece_type: piece.piece_type,
-        promoted: piece.promoted,
-        promotion,
-        captured: None,
-    });
+    #[test]
+    fn perft_chess_start() {
+        let board = bench::position("chess");
+        assert_eq!(
+            [1, 2, 3, 4].map(|depth| perft(&board, depth)),
+            [20, 400, 8902, 197281]
+        );
+    }
 }
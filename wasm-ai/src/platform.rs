@@ -0,0 +1,28 @@
+//! Host services that differ between the browser build and native builds
+//! (benchmarks, tests)
+
+/// Milliseconds since the Unix epoch
+#[cfg(target_arch = "wasm32")]
+pub fn now_ms() -> f64 {
+    js_sys::Date::now()
+}
+
+/// Milliseconds since the Unix epoch
+#[cfg(not(target_arch = "wasm32"))]
+pub fn now_ms() -> f64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0.0, |d| d.as_secs_f64() * 1000.0)
+}
+
+/// Write a line to the browser console (stderr on native builds)
+#[cfg(target_arch = "wasm32")]
+pub fn log(message: &str) {
+    web_sys::console::log_1(&message.into());
+}
+
+/// Write a line to the browser console (stderr on native builds)
+#[cfg(not(target_arch = "wasm32"))]
+pub fn log(message: &str) {
+    eprintln!("{}", message);
+}
@@ -67,7 +67,15 @@ const PST_GENERIC: [i32; 81] = [
 
 /// Get PST value for a piece at a given position
 /// player: 1 = Player1 (bottom), 2 = Player2 (top)
-pub fn get_pst_value(piece_type: &PieceType, row: usize, col: usize, player: u8) -> i32 {
+/// Boards other than 9x9 are scaled onto the 9x9 tables
+pub fn get_pst_value(
+    piece_type: &PieceType,
+    row: usize,
+    col: usize,
+    player: u8,
+    rows: usize,
+    cols: usize,
+) -> i32 {
     // Select appropriate table
     let table = match piece_type {
         PieceType::Pawn => &PST_S_PAWN,
@@ -77,13 +85,13 @@ pub fn get_pst_value(piece_type: &PieceType, row: usize, col: usize, player: u8)
         _ => &PST_GENERIC,
     };
 
-    // Calculate index (mirror for Player 2)
-    let idx = if player == 1 {
-        row * 9 + col
+    // Player 2: mirror both row and col
+    let (row, col) = if player == 1 {
+        (row, col)
     } else {
-        // Player 2: mirror both row and col
-        (8 - row) * 9 + (8 - col)
+        (rows - 1 - row, cols - 1 - col)
     };
+    let idx = scale_to_9(row, rows) * 9 + scale_to_9(col, cols);
 
     // Bounds check
     if idx >= 81 {
@@ -92,3 +100,12 @@ pub fn get_pst_value(piece_type: &PieceType, row: usize, col: usize, player: u8)
 
     table[idx]
 }
+
+/// Map a coordinate on an edge of `len` squares onto 0..9
+fn scale_to_9(coord: usize, len: usize) -> usize {
+    if len == 9 || len < 2 {
+        coord
+    } else {
+        (coord * 8 + (len - 1) / 2) / (len - 1)
+    }
+}
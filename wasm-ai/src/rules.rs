@@ -0,0 +1,420 @@
+use crate::types::*;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Largest supported board edge (squares per row/column)
+pub const MAX_BOARD_SIZE: usize = 11;
+
+/// Largest supported `check_limit`
+pub const MAX_CHECK_LIMIT: u8 = 16;
+
+/// Variant definition loaded at runtime
+///
+/// Every field has a default matching the classic hybrid rules, so a JSON
+/// object only needs to list what differs, e.g. `{"stalemate": "draw"}`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct RuleSet {
+    /// Board rows (0 = take from the board input)
+    pub rows: usize,
+    /// Board columns (0 = take from the board input)
+    pub cols: usize,
+    pub hands: HandRules,
+    pub promotion_zones: PromotionZones,
+    pub promotion: PromotionRules,
+    /// Rows from which a chess pawn may advance two squares (None = never)
+    pub pawn_double_step_rows: [Option<usize>; 2],
+    pub draw: DrawRules,
+    pub stalemate: StalemateOutcome,
+    pub win_conditions: Vec<WinCondition>,
+    /// Squares a king must reach for `king_of_the_hill` (empty = board centre)
+    pub hill_squares: Vec<Position>,
+    /// Square each player's king must reach for `try` (None = centre of the
+    /// opponent's back rank, where the king starts in standard shogi)
+    pub try_squares: [Option<Position>; 2],
+    /// Checks needed to win by `three_check`
+    pub check_limit: u8,
+    /// Pieces that must not be checkmated or captured
+    pub royal_pieces: Vec<PieceType>,
+    pub royal_safety: RoyalSafety,
+    pub pruning: PruningMargins,
+}
+
+impl Default for RuleSet {
+    fn default() -> Self {
+        Self {
+            rows: 0,
+            cols: 0,
+            hands: HandRules::default(),
+            promotion_zones: PromotionZones::default(),
+            promotion: PromotionRules::default(),
+            pawn_double_step_rows: [Some(6), Some(1)],
+            draw: DrawRules::default(),
+            stalemate: StalemateOutcome::Loss,
+            win_conditions: vec![WinCondition::Checkmate],
+            hill_squares: Vec::new(),
+            try_squares: [None, None],
+            check_limit: 3,
+            royal_pieces: vec![PieceType::King, PieceType::ChessKing],
+            royal_safety: RoyalSafety::All,
+            pruning: PruningMargins::default(),
+        }
+    }
+}
+
+/// Captured pieces and drops
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct HandRules {
+    /// Whether player 1 keeps captured pieces in hand
+    pub player1: bool,
+    /// Whether player 2 keeps captured pieces in hand
+    pub player2: bool,
+    /// Forbid two unpromoted shogi pawns of one player on the same file
+    pub nifu: bool,
+    /// Forbid mating with a shogi pawn drop
+    pub no_pawn_drop_mate: bool,
+}
+
+impl Default for HandRules {
+    fn default() -> Self {
+        Self {
+            player1: true,
+            player2: true,
+            nifu: true,
+            no_pawn_drop_mate: true,
+        }
+    }
+}
+
+/// Promotion zone for one player (same format as the board editor)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PromotionZone {
+    pub rows: usize,
+    pub from_top: bool,
+}
+
+/// Promotion zones per piece family for one player
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(from = "ZoneSpec")]
+pub struct PlayerZones {
+    pub shogi: PromotionZone,
+    pub chess: PromotionZone,
+}
+
+/// The editor stores either one zone for all pieces or one per family
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ZoneSpec {
+    PerFamily {
+        shogi: PromotionZone,
+        chess: PromotionZone,
+    },
+    Single(PromotionZone),
+}
+
+impl From<ZoneSpec> for PlayerZones {
+    fn from(spec: ZoneSpec) -> Self {
+        match spec {
+            ZoneSpec::PerFamily { shogi, chess } => Self { shogi, chess },
+            ZoneSpec::Single(zone) => Self {
+                shogi: zone,
+                chess: zone,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct PromotionZones {
+    pub player1: PlayerZones,
+    pub player2: PlayerZones,
+}
+
+impl Default for PromotionZones {
+    fn default() -> Self {
+        let zones = |from_top| PlayerZones {
+            shogi: PromotionZone { rows: 3, from_top },
+            chess: PromotionZone { rows: 1, from_top },
+        };
+        Self {
+            player1: zones(true),
+            player2: zones(false),
+        }
+    }
+}
+
+/// What pieces promote and into what
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct PromotionRules {
+    /// How each promotable piece moves once flipped, e.g. `{"pawn": "gold"}`;
+    /// it keeps its own type and returns to hand unpromoted when captured
+    pub mappings: BTreeMap<PieceType, Movement>,
+    /// Pieces a chess pawn may become inside the zone
+    pub chess_pawn: Vec<PieceType>,
+}
+
+impl Default for PromotionRules {
+    fn default() -> Self {
+        Self {
+            mappings: BTreeMap::from([
+                (PieceType::Rook, Movement::Dragon),
+                (PieceType::Bishop, Movement::Horse),
+                (PieceType::Silver, Movement::Gold),
+                (PieceType::Knight, Movement::Gold),
+                (PieceType::Lance, Movement::Gold),
+                (PieceType::Pawn, Movement::Gold),
+            ]),
+            chess_pawn: vec![
+                PieceType::ChessQueen,
+                PieceType::ChessRook,
+                PieceType::ChessBishop,
+                PieceType::ChessKnight,
+            ],
+        }
+    }
+}
+
+/// Draw rules in force
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct DrawRules {
+    /// Score a position repeated along the search path as a draw
+    pub repetition: bool,
+    /// Draw once this many plies have been played (0 = no limit)
+    pub move_limit: u32,
+}
+
+/// Forward-pruning margins for the search, in centipawns (0 = off)
+///
+/// Variants where drops and promotions swing the score more per move need
+/// wider margins to prune safely.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct PruningMargins {
+    /// Reverse futility: skip nodes this far above beta per ply of depth
+    pub reverse_futility: i32,
+    /// Futility: skip quiet moves this far below alpha per ply of depth
+    pub futility: i32,
+    /// Razoring: drop into quiescence this far below alpha per ply of depth
+    pub razoring: i32,
+    /// Late-move pruning: quiet moves tried at depth 1 (times depth squared deeper)
+    pub late_moves: usize,
+    /// Delta pruning: skip quiescence captures that gain less than this
+    /// over what is needed to reach alpha
+    pub delta: i32,
+}
+
+impl Default for PruningMargins {
+    fn default() -> Self {
+        Self {
+            reverse_futility: 120,
+            futility: 150,
+            razoring: 300,
+            late_moves: 16,
+            delta: 200,
+        }
+    }
+}
+
+/// Result for the side to move when it has no legal moves and is not in check
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StalemateOutcome {
+    Loss,
+    Draw,
+    Win,
+}
+
+/// Ways to win the game
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WinCondition {
+    Checkmate,
+    /// Win by capturing the king; moves into check are legal
+    KingCapture,
+    /// Win by moving a king onto a hill square
+    KingOfTheHill,
+    /// Win by moving a king onto the opponent king's start square
+    Try,
+    /// Win by giving `check_limit` checks
+    ThreeCheck,
+    /// Win by leaving the opponent without pieces on the board or in hand
+    CaptureAll,
+}
+
+/// Which royals must stay safe when a player has more than one
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RoyalSafety {
+    /// Every royal must be safe; losing any one loses the game
+    All,
+    /// One safe royal is enough; the game is lost with the last one
+    Any,
+}
+
+impl RuleSet {
+    /// Fill in the board dimensions and check them against the input board
+    pub fn resolve(mut self, rows: usize, cols: usize) -> Result<Self, String> {
+        if self.rows == 0 {
+            self.rows = rows;
+        }
+        if self.cols == 0 {
+            self.cols = cols;
+        }
+        if self.rows != rows || self.cols != cols {
+            return Err(format!(
+                "Board is {}x{} but rules expect {}x{}",
+                rows, cols, self.rows, self.cols
+            ));
+        }
+        if rows > MAX_BOARD_SIZE || cols > MAX_BOARD_SIZE {
+            return Err(format!(
+                "Board larger than {}x{} is not supported",
+                MAX_BOARD_SIZE, MAX_BOARD_SIZE
+            ));
+        }
+        if self.check_limit == 0 || self.check_limit > MAX_CHECK_LIMIT {
+            return Err(format!(
+                "checkLimit must be between 1 and {}",
+                MAX_CHECK_LIMIT
+            ));
+        }
+        if self.win_conditions.is_empty() {
+            return Err("At least one win condition is required".to_string());
+        }
+        if self
+            .hill_squares
+            .iter()
+            .any(|pos| pos.row >= rows || pos.col >= cols)
+        {
+            return Err("hillSquares must lie on the board".to_string());
+        }
+        if self.hill_squares.is_empty() {
+            // Centre square(s): one line on odd edges, two on even edges
+            let centre = |len: usize| {
+                if len.is_multiple_of(2) {
+                    len / 2 - 1..=len / 2
+                } else {
+                    len / 2..=len / 2
+                }
+            };
+            self.hill_squares = centre(rows)
+                .flat_map(|row| centre(cols).map(move |col| Position { row, col }))
+                .collect();
+        }
+        Ok(self)
+    }
+
+    /// Stable hash of the rule set (FNV-1a of its JSON form)
+    pub fn fingerprint(&self) -> u64 {
+        let json = serde_json::to_string(self).unwrap_or_default();
+        json.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+        })
+    }
+
+    pub fn has_hand(&self, player: Player) -> bool {
+        if player == 1 {
+            self.hands.player1
+        } else {
+            self.hands.player2
+        }
+    }
+
+    pub fn wins_by(&self, condition: WinCondition) -> bool {
+        self.win_conditions.contains(&condition)
+    }
+
+    pub fn is_royal(&self, piece_type: PieceType) -> bool {
+        self.royal_pieces.contains(&piece_type)
+    }
+
+    /// Whether moves must not leave the own king in check
+    pub fn check_legality(&self) -> bool {
+        !self.wins_by(WinCondition::KingCapture)
+    }
+
+    pub fn is_hill_square(&self, pos: Position) -> bool {
+        self.hill_squares.contains(&pos)
+    }
+
+    /// Square `player`'s king must reach to win by the try rule
+    ///
+    /// Defaults to the centre of the opponent's back rank; boards whose kings
+    /// start elsewhere must set `trySquares`.
+    pub fn try_square(&self, player: Player) -> Position {
+        self.try_squares[(player - 1) as usize].unwrap_or(Position {
+            row: if player == 1 { 0 } else { self.rows - 1 },
+            col: self.cols / 2,
+        })
+    }
+
+    /// Whether `row` lies in the promotion zone of `player` for this piece family
+    pub fn in_promotion_zone(&self, piece_type: PieceType, player: Player, row: usize) -> bool {
+        let zones = if player == 1 {
+            &self.promotion_zones.player1
+        } else {
+            &self.promotion_zones.player2
+        };
+        let zone = if is_chess_piece(piece_type) {
+            zones.chess
+        } else {
+            zones.shogi
+        };
+
+        if zone.from_top {
+            row < zone.rows
+        } else {
+            row + zone.rows >= self.rows
+        }
+    }
+
+    /// Whether a piece may flip to its promoted side
+    pub fn promotes(&self, piece_type: PieceType) -> bool {
+        self.promotion.mappings.contains_key(&piece_type)
+    }
+
+    /// How a piece moves, following the promotion table for promoted pieces
+    pub fn movement(&self, piece: &Piece) -> Movement {
+        if piece.promoted {
+            if let Some(&movement) = self.promotion.mappings.get(&piece.piece_type) {
+                return movement;
+            }
+        }
+        piece.piece_type.movement()
+    }
+
+    /// Rows left in front of a piece on `row` moving towards the opponent
+    pub fn rows_ahead(&self, player: Player, row: usize) -> usize {
+        if player == 1 {
+            row
+        } else {
+            self.rows - 1 - row
+        }
+    }
+
+    /// Whether an unpromoted piece on `row` would have no move left
+    pub fn is_dead_square(&self, piece_type: PieceType, player: Player, row: usize) -> bool {
+        let ahead = self.rows_ahead(player, row);
+        match piece_type {
+            PieceType::Pawn | PieceType::Lance | PieceType::ChessPawn => ahead == 0,
+            PieceType::Knight => ahead < 2,
+            _ => false,
+        }
+    }
+}
+
+pub fn is_chess_piece(piece_type: PieceType) -> bool {
+    matches!(
+        piece_type,
+        PieceType::ChessKing
+            | PieceType::ChessQueen
+            | PieceType::ChessRook
+            | PieceType::ChessBishop
+            | PieceType::ChessKnight
+            | PieceType::ChessPawn
+    )
+}
@@ -1,195 +1,625 @@
 use crate::board::Board;
 use crate::config::AIConfig;
-use crate::eval::evaluate;
-use crate::moves::generate_moves;
+use crate::eval::{capture_gain, evaluate, exchange_value};
+use crate::history::History;
+use crate::moves::{generate_moves, generate_moves_into, goal_winner};
+use crate::platform::{log, now_ms};
+use crate::rules::StalemateOutcome;
+use crate::see::see;
 use crate::tt::{Bound, TranspositionTable};
 use crate::types::*;
-use crate::zobrist;
 use wasm_bindgen::JsValue;
-use web_sys;
 
 const INFINITY: i32 = 1_000_000;
-const MATE_SCORE: i32 = 100_000;
+pub const MATE_SCORE: i32 = 100_000;
 const MAX_PLY: usize = 64;
+/// Scores beyond this are mates (at most MAX_PLY plies away)
+const MATE_BOUND: i32 = MATE_SCORE - MAX_PLY as i32;
+/// Initial capacity of each per-ply move buffer (enough for shogi positions)
+const MAX_MOVES: usize = 600;
+/// Minimum time between progress reports within an iteration
+const INFO_INTERVAL_MS: f64 = 500.0;
+/// Ordering score left on moves the move loop skipped (pruned, excluded or
+/// unplayable), so only searched quiet moves lose history on a cutoff
+const UNSEARCHED: i32 = i32::MIN;
+/// Initial half-width of the aspiration window around the last score
+const ASPIRATION_DELTA: i32 = 100;
+/// Shallowest depth at which the TT move is tested for being singular
+const SINGULAR_MIN_DEPTH: u8 = 4;
+/// How far per ply of depth the alternatives must fall short of the TT move
+const SINGULAR_MARGIN: i32 = 20;
+
+/// Deepest remaining depth for reverse futility pruning
+const REVERSE_FUTILITY_MAX_DEPTH: u8 = 3;
+/// Deepest remaining depth for razoring
+const RAZORING_MAX_DEPTH: u8 = 2;
+/// Deepest remaining depth for futility pruning
+const FUTILITY_MAX_DEPTH: u8 = 2;
+/// Deepest remaining depth for late-move pruning
+const LATE_MOVE_MAX_DEPTH: u8 = 3;
+/// Quiescence plies that also search quiet checks and promotions
+const QSEARCH_CHECK_PLIES: u8 = 1;
+
+/// What the search knows about one ply of the current path
+#[derive(Clone, Copy, Default)]
+struct PlyInfo {
+    played: Option<Move>,   // Move being searched from this ply
+    captured: bool,         // Whether `played` captures
+    extensions: u8,         // Plies of extension on the path to this ply
+    excluded: Option<Move>, // Move left out by a singular-extension search
+}
 
 /// Search state to track killer moves and other search data
-struct SearchState {
-    tt: TranspositionTable,
+struct SearchState<'a> {
+    tt: &'a mut TranspositionTable,
     killer_moves: Vec<[Option<Move>; 2]>,
-    history: Vec<Vec<i32>>, // [from_square][to_square] -> score
+    history: &'a mut History,   // Quiet-move ordering statistics
+    path: Vec<u64>,             // Position hashes from the root to the current node
+    path_start: usize,          // First `path` entry after the last null move
+    move_stack: Vec<Vec<Move>>, // Per-ply move buffers, reused across nodes
+    score_stack: Vec<Vec<i32>>, // Ordering scores parallel to `move_stack`
+    pv: Vec<Vec<Move>>,         // Triangular PV table: best line found from each ply
+    stack: Vec<PlyInfo>,        // Indexed by ply, one past MAX_PLY
     nodes_searched: usize,
     start_time: f64,
     timeout_ms: u32,
     timed_out: bool,
+    on_info: &'a mut dyn FnMut(&SearchInfo),
+    last_info: f64,      // When progress was last reported
+    seldepth: usize,     // Deepest ply reached in the current iteration
+    completed_depth: u8, // Last iteration that finished, with its lines
+    lines: Vec<SearchLine>,
 }
 
-impl SearchState {
-    fn new(config: &AIConfig) -> Self {
-        // Initialize history table for 8x8 board (64 squares)
-        let history = vec![vec![0; 64]; 64];
-
+impl<'a> SearchState<'a> {
+    fn new(
+        config: &AIConfig,
+        tt: &'a mut TranspositionTable,
+        history: &'a mut History,
+        on_info: &'a mut dyn FnMut(&SearchInfo),
+    ) -> Self {
         SearchState {
-            tt: TranspositionTable::new(config.tt_size_mb),
+            tt,
             killer_moves: vec![[None, None]; MAX_PLY],
             history,
+            path: Vec::with_capacity(MAX_PLY),
+            path_start: 0,
+            move_stack: (0..MAX_PLY)
+                .map(|_| Vec::with_capacity(MAX_MOVES))
+                .collect(),
+            score_stack: (0..MAX_PLY)
+                .map(|_| Vec::with_capacity(MAX_MOVES))
+                .collect(),
+            pv: (0..MAX_PLY).map(|_| Vec::with_capacity(MAX_PLY)).collect(),
+            stack: vec![PlyInfo::default(); MAX_PLY + 1],
             nodes_searched: 0,
-            start_time: js_sys::Date::now(),
+            start_time: now_ms(),
             timeout_ms: config.timeout_ms,
             timed_out: false,
+            on_info,
+            last_info: now_ms(),
+            seldepth: 0,
+            completed_depth: 0,
+            lines: Vec::new(),
+        }
+    }
+
+    /// Report the last completed iteration's lines with the current counters
+    fn report(&mut self) {
+        self.last_info = now_ms();
+        let hashfull = self.tt.hashfull();
+        for (i, line) in self.lines.iter().enumerate() {
+            let info = SearchInfo {
+                multipv: i + 1,
+                depth: self.completed_depth,
+                seldepth: self.seldepth,
+                score: line.score,
+                bound: Bound::Exact,
+                nodes: self.nodes_searched,
+                time_ms: self.last_info - self.start_time,
+                hashfull,
+                pv: &line.pv,
+            };
+            (self.on_info)(&info);
         }
     }
 
+    /// Report an aspiration window failing at `depth` (the search goes on)
+    fn report_bound(&mut self, depth: u8, score: i32, bound: Bound, pv: &[Move]) {
+        self.last_info = now_ms();
+        let info = SearchInfo {
+            multipv: 1,
+            depth,
+            seldepth: self.seldepth,
+            score,
+            bound,
+            nodes: self.nodes_searched,
+            time_ms: self.last_info - self.start_time,
+            hashfull: self.tt.hashfull(),
+            pv,
+        };
+        (self.on_info)(&info);
+    }
+
     fn is_timeout(&self) -> bool {
-        let elapsed = js_sys::Date::now() - self.start_time;
+        let elapsed = now_ms() - self.start_time;
         elapsed >= self.timeout_ms as f64
     }
+
+    /// Count a node at `ply`, checking the clock every 1000 nodes; false
+    /// once the search has run out of time
+    fn enter_node(&mut self, ply: usize) -> bool {
+        if self.timed_out {
+            return false;
+        }
+        self.nodes_searched += 1;
+        self.seldepth = self.seldepth.max(ply);
+        self.clear_pv(ply);
+
+        if self.nodes_searched.is_multiple_of(1000) {
+            if self.is_timeout() {
+                self.timed_out = true;
+                return false;
+            }
+            if self.completed_depth > 0 && now_ms() - self.last_info >= INFO_INTERVAL_MS {
+                self.report();
+            }
+        }
+        true
+    }
+
+    /// Record the node at `hash` passing the turn; positions before a null
+    /// move never count as repeated below it. Returns what to hand back to
+    /// `leave_null_move`.
+    fn enter_null_move(&mut self, hash: u64) -> usize {
+        self.path.push(hash);
+        std::mem::replace(&mut self.path_start, self.path.len())
+    }
+
+    fn leave_null_move(&mut self, saved_start: usize) {
+        self.path.pop();
+        self.path_start = saved_start;
+    }
+
+    /// Borrow the move and score buffers of `ply` (hand back with `return_buffers`)
+    fn take_buffers(&mut self, ply: usize) -> (Vec<Move>, Vec<i32>) {
+        match (self.move_stack.get_mut(ply), self.score_stack.get_mut(ply)) {
+            (Some(moves), Some(scores)) => (std::mem::take(moves), std::mem::take(scores)),
+            _ => (Vec::new(), Vec::new()),
+        }
+    }
+
+    fn return_buffers(&mut self, ply: usize, moves: Vec<Move>, scores: Vec<i32>) {
+        if ply < self.move_stack.len() {
+            self.move_stack[ply] = moves;
+            self.score_stack[ply] = scores;
+        }
+    }
+
+    /// Record `m` as searched from `ply` and set up the child's ply, which
+    /// inherits this path's extensions plus `extension`
+    fn enter_child(&mut self, ply: usize, m: Option<Move>, captured: bool, extension: u8) {
+        let extensions = self.stack[ply].extensions + extension;
+        self.stack[ply].played = m;
+        self.stack[ply].captured = captured;
+        self.stack[ply + 1] = PlyInfo {
+            extensions,
+            ..PlyInfo::default()
+        };
+    }
+
+    /// Move played one ply before `ply` (`None` after a null move and at
+    /// the root)
+    fn previous_move(&self, ply: usize) -> Option<Move> {
+        ply.checked_sub(1)
+            .and_then(|p| self.stack.get(p))
+            .and_then(|info| info.played)
+    }
+
+    /// Whether the path to `ply` may still be extended
+    fn can_extend(&self, ply: usize, config: &AIConfig) -> bool {
+        self.stack[ply].extensions < config.extensions.budget
+    }
+
+    fn clear_pv(&mut self, ply: usize) {
+        if let Some(line) = self.pv.get_mut(ply) {
+            line.clear();
+        }
+    }
+
+    /// Make `m` followed by the child's line the PV of `ply`
+    fn update_pv(&mut self, ply: usize, m: Move) {
+        if ply >= self.pv.len() {
+            return;
+        }
+        let (head, tail) = self.pv.split_at_mut(ply + 1);
+        let line = &mut head[ply];
+        line.clear();
+        line.push(m);
+        if let Some(child) = tail.first() {
+            line.extend_from_slice(child);
+        }
+    }
+}
+
+/// Heap memory a search uses besides the transposition table and history
+pub fn state_memory_bytes() -> usize {
+    use std::mem::size_of;
+    let move_buffers = MAX_PLY * MAX_MOVES * (size_of::<Move>() + size_of::<i32>());
+    let stack = (MAX_PLY + 1) * size_of::<PlyInfo>();
+    let killers = MAX_PLY * size_of::<[Option<Move>; 2]>();
+    let pv = MAX_PLY * MAX_PLY * size_of::<Move>();
+    move_buffers + stack + killers + pv
+}
+
+/// A root move with its score and expected continuation
+#[derive(Clone, Debug)]
+pub struct SearchLine {
+    /// Score for the side to move
+    pub score: i32,
+    /// Never empty; starts with the root move
+    pub pv: Vec<Move>,
+}
+
+/// Outcome of a search
+pub struct SearchResult {
+    /// Best first; more than one in multi-PV mode
+    pub lines: Vec<SearchLine>,
+    /// Last fully searched depth
+    pub depth: u8,
+    pub nodes: usize,
+}
+
+impl SearchResult {
+    pub fn best_move(&self) -> Move {
+        self.lines[0].pv[0]
+    }
+}
+
+/// Progress of a running search
+pub struct SearchInfo<'a> {
+    /// Rank of the line, from 1
+    pub multipv: usize,
+    pub depth: u8,
+    /// Deepest ply reached, quiescence included
+    pub seldepth: usize,
+    pub score: i32,
+    pub bound: Bound,
+    pub nodes: usize,
+    pub time_ms: f64,
+    pub hashfull: u32,
+    pub pv: &'a [Move],
+}
+
+/// Score as shown to the user: centipawns, or moves to mate (negative when
+/// the side to move is getting mated)
+pub fn display_score(score: i32) -> Score {
+    if score >= MATE_BOUND {
+        Score::Mate((MATE_SCORE - score + 1) / 2)
+    } else if score <= -MATE_BOUND {
+        Score::Mate(-(MATE_SCORE + score) / 2)
+    } else {
+        Score::Cp(score)
+    }
 }
 
 /// Find the best move using advanced search
-pub fn find_best_move(board: &Board, player: Player, config: &AIConfig) -> Result<Move, JsValue> {
+///
+/// `tt` is kept by the caller, so later searches reuse what this one learnt.
+/// `on_info` hears about every completed iteration, and about the node count
+/// every `INFO_INTERVAL_MS` in between.
+pub fn find_best_move(
+    board: &Board,
+    player: Player,
+    config: &AIConfig,
+    tt: &mut TranspositionTable,
+    history: &mut History,
+    on_info: &mut dyn FnMut(&SearchInfo),
+) -> Result<SearchResult, JsValue> {
     let moves = generate_moves(board);
 
     if moves.is_empty() {
         return Err(to_js_error("No legal moves available"));
     }
 
-    let mut state = SearchState::new(config);
-    let mut best_move = moves[0].clone();
-    let mut best_score = -INFINITY;
+    tt.set_variant(board.rules.fingerprint());
+    tt.new_search();
+    history.new_search(board.rows(), board.cols());
+    let mut state = SearchState::new(config, tt, history, on_info);
+    state.path.push(board.hash);
+    state.lines.push(SearchLine {
+        score: 0,
+        pv: vec![moves[0]],
+    });
 
-    web_sys::console::log_1(
-        &format!(
-            "Starting search: max_depth={}, legal_moves={}",
-            config.max_depth,
-            moves.len()
-        )
-        .into(),
-    );
+    log(&format!(
+        "Starting search: max_depth={}, legal_moves={}",
+        config.max_depth,
+        moves.len()
+    ));
 
     // Iterative Deepening
     for depth in 1..=config.max_depth {
         // Check timeout before starting new depth
         if state.is_timeout() {
-            web_sys::console::log_1(&format!("Timeout reached at depth {}", depth).into());
+            log(&format!("Timeout reached at depth {}", depth));
             state.timed_out = true;
             break;
         }
 
-        let (score, mv) = search_root(board, player, depth, config, &mut state);
+        state.seldepth = 0;
+        let lines = search_aspiration(board, player, depth, config, &mut state);
 
-        if let Some(m) = mv {
-            best_move = m;
-            best_score = score;
+        // An interrupted iteration scored its unfinished moves as 0, so it
+        // only counts when there is nothing better
+        if state.timed_out && state.completed_depth > 0 {
+            log(&format!("Timeout during depth {}", depth));
+            break;
         }
 
-        web_sys::console::log_1(
-            &format!(
-                "Depth {} complete: score={}, nodes={}",
-                depth, best_score, state.nodes_searched
-            )
-            .into(),
-        );
+        if !lines.is_empty() {
+            state.lines = lines;
+            state.completed_depth = depth;
+        }
+        state.report();
+
+        let best = &state.lines[0];
+        log(&format!(
+            "Depth {} complete: score={}, nodes={}, hashfull={}, pv={:?}",
+            depth,
+            best.score,
+            state.nodes_searched,
+            state.tt.hashfull(),
+            best.pv
+        ));
 
         // Early exit if we found a mate
-        if best_score.abs() > MATE_SCORE - 100 {
-            web_sys::console::log_1(&"Early exit: mate found".into());
+        if best.score.abs() >= MATE_BOUND {
+            log("Early exit: mate found");
             break;
         }
 
         // Check timeout after completing depth
         if state.is_timeout() {
-            web_sys::console::log_1(&format!("Timeout after depth {}", depth).into());
+            log(&format!("Timeout after depth {}", depth));
             state.timed_out = true;
             break;
         }
     }
 
-    web_sys::console::log_1(
-        &format!("Search finished: total_nodes={}", state.nodes_searched).into(),
-    );
+    log(&format!(
+        "Search finished: total_nodes={}",
+        state.nodes_searched
+    ));
 
-    Ok(best_move)
+    Ok(SearchResult {
+        lines: state.lines,
+        depth: state.completed_depth,
+        nodes: state.nodes_searched,
+    })
 }
 
-/// Root search with iterative deepening
+/// Search an iteration with a window around the last iteration's score,
+/// widening it on the side the score falls out of
+fn search_aspiration(
+    board: &Board,
+    player: Player,
+    depth: u8,
+    config: &AIConfig,
+    state: &mut SearchState,
+) -> Vec<SearchLine> {
+    let last_score = state.lines[0].score;
+    let mut delta = ASPIRATION_DELTA;
+    let (mut alpha, mut beta) =
+        if state.completed_depth == 0 || config.multipv > 1 || last_score.abs() >= MATE_BOUND {
+            (-INFINITY, INFINITY)
+        } else {
+            (last_score - delta, last_score + delta)
+        };
+
+    loop {
+        let lines = search_root(board, player, depth, alpha, beta, config, state);
+        if state.timed_out {
+            return lines;
+        }
+
+        match lines.first() {
+            // Fail low: every move scored at most alpha
+            None if alpha > -INFINITY => {
+                let pv = [state.lines[0].pv[0]];
+                state.report_bound(depth, alpha, Bound::Upper, &pv);
+                alpha = (alpha - delta).max(-INFINITY);
+            }
+            // Fail high: the first line is only a lower bound
+            Some(best) if best.score >= beta => {
+                state.report_bound(depth, beta, Bound::Lower, &best.pv);
+                beta = (beta + delta).min(INFINITY);
+            }
+            _ => return lines,
+        }
+        delta *= 2;
+        if delta > ASPIRATION_DELTA * 16 {
+            (alpha, beta) = (-INFINITY, INFINITY);
+        }
+    }
+}
+
+/// Root search: the best `config.multipv` lines scoring within
+/// `alpha..beta`, best first
+///
+/// Stops at the first move scoring `beta` or more, returning it on top.
+#[allow(clippy::too_many_arguments)]
 fn search_root(
     board: &Board,
     player: Player,
     depth: u8,
+    alpha: i32,
+    beta: i32,
     config: &AIConfig,
     state: &mut SearchState,
-) -> (i32, Option<Move>) {
-    let mut moves = generate_moves(board);
+) -> Vec<SearchLine> {
+    state.clear_pv(0);
+    let (mut moves, mut scores) = state.take_buffers(0);
+    generate_moves_into(board, &mut moves);
 
     if moves.is_empty() {
-        return (-MATE_SCORE, None);
+        state.return_buffers(0, moves, scores);
+        return Vec::new();
     }
 
     // Order moves (TT move first if available)
-    let hash = zobrist::get_zobrist().hash(board);
-    if let Some(tt_entry) = state.tt.get(hash) {
-        if let Some(ref tt_move) = tt_entry.best_move {
-            // Move TT move to front
-            if let Some(pos) = moves.iter().position(|m| m == tt_move) {
-                moves.swap(0, pos);
-            }
+    let hash = board.hash;
+    let tt_move = state.tt.get(hash).and_then(|e| e.best_move);
+    score_moves(
+        board,
+        &moves,
+        &mut scores,
+        tt_move.as_ref(),
+        0,
+        config,
+        state,
+    );
+
+    // The other lines of the last iteration follow, in their order
+    for (rank, line) in state.lines.iter().enumerate().skip(1) {
+        if let Some(k) = moves.iter().position(|&m| m == line.pv[0]) {
+            scores[k] = 900_000 - rank as i32;
         }
     }
 
-    order_moves(board, &mut moves, None, 0, config, state);
+    let lines_wanted = config.multipv.clamp(1, moves.len());
+    let mut lines: Vec<SearchLine> = Vec::with_capacity(lines_wanted + 1);
 
-    let mut best_move = None;
-    let mut best_score = -INFINITY;
-    let mut alpha = -INFINITY;
-    let beta = INFINITY;
-
-    for m in moves {
+    for i in 0..moves.len() {
+        let m = pick_move(&mut moves, &mut scores, i);
         let mut new_board = board.clone();
-        let captured = new_board.get(m.to).map(|p| p.piece_type);
+        let captured = new_board.get(m.to()).copied();
 
         if new_board.make_move(&m).is_err() {
             continue;
         }
+        state.enter_child(0, Some(m), captured.is_some(), 0);
 
-        let score = -alpha_beta(
-            &new_board,
-            depth - 1,
-            -beta,
-            -alpha,
-            3 - player,
-            1,
-            config,
-            state,
-        );
+        // Once all lines are filled, a move only has to be shown no better
+        // than the worst of them (null window), unless it turns out better
+        let filled = lines.len() == lines_wanted;
+        let worst = if filled {
+            lines[lines_wanted - 1].score.max(alpha)
+        } else {
+            alpha
+        };
+        let mut score = -INFINITY;
+        if filled {
+            score = -alpha_beta(
+                &new_board,
+                depth - 1,
+                -worst - 1,
+                -worst,
+                3 - player,
+                1,
+                config,
+                state,
+            );
+        }
+        if !filled || (score > worst && score < beta) {
+            score = -alpha_beta(
+                &new_board,
+                depth - 1,
+                -beta,
+                -worst,
+                3 - player,
+                1,
+                config,
+                state,
+            );
+        }
 
         new_board.unmake_move(&m, captured).ok();
 
-        if score > best_score {
-            best_score = score;
-            best_move = Some(m.clone());
+        if score > worst {
+            state.update_pv(0, m);
+            let rank = lines
+                .iter()
+                .position(|line| score > line.score)
+                .unwrap_or(lines.len());
+            lines.insert(
+                rank,
+                SearchLine {
+                    score,
+                    pv: state.pv[0].clone(),
+                },
+            );
+            lines.truncate(lines_wanted);
+
+            if score >= beta {
+                break;
+            }
         }
+    }
+    state.return_buffers(0, moves, scores);
 
-        alpha = alpha.max(best_score);
+    for line in &mut lines {
+        extend_pv(board, &mut line.pv, state);
+    }
 
-        if alpha >= beta {
-            break;
+    // Store in TT (an interrupted iteration's scores are not trusted)
+    if config.use_tt && !state.timed_out {
+        if let Some(best) = lines.first() {
+            let bound = if best.score >= beta {
+                Bound::Lower
+            } else {
+                Bound::Exact
+            };
+            state
+                .tt
+                .store(hash, depth, best.score, bound, Some(best.pv[0]));
         }
     }
 
-    // Store in TT
-    if config.use_tt {
-        state
-            .tt
-            .store(hash, depth, best_score, Bound::Exact, best_move.clone());
+    lines
+}
+
+/// Continue `pv` with the table's best moves; a TT cutoff at a PV node
+/// ends the searched line early
+///
+/// Stops at a move that is not legal in the position reached, at a
+/// position already on the line and once the game is decided.
+fn extend_pv(root: &Board, pv: &mut Vec<Move>, state: &mut SearchState) {
+    let mut board = root.clone();
+    let mut seen = [0u64; MAX_PLY];
+    for (ply, m) in pv.iter().enumerate() {
+        seen[ply] = board.hash;
+        if board.make_move(m).is_err() {
+            return;
+        }
     }
 
-    (best_score, best_move)
+    while pv.len() < MAX_PLY {
+        let ply = pv.len();
+        if seen[..ply].contains(&board.hash) || goal_winner(&board).is_some() {
+            break;
+        }
+        seen[ply] = board.hash;
+        let Some(m) = state.tt.get(board.hash).and_then(|entry| entry.best_move) else {
+            break;
+        };
+
+        let (mut moves, scores) = state.take_buffers(ply);
+        generate_moves_into(&board, &mut moves);
+        let legal = moves.contains(&m);
+        state.return_buffers(ply, moves, scores);
+        if !legal || board.make_move(&m).is_err() {
+            break;
+        }
+        pv.push(m);
+    }
 }
 
 /// Alpha-Beta search with enhancements
+#[allow(clippy::too_many_arguments)]
 fn alpha_beta(
     board: &Board,
-    depth: u8,
+    mut depth: u8,
     mut alpha: i32,
     beta: i32,
     player: Player,
@@ -197,54 +627,111 @@ fn alpha_beta(
     config: &AIConfig,
     state: &mut SearchState,
 ) -> i32 {
-    state.nodes_searched += 1;
-
-    // Check timeout every 1000 nodes to avoid overhead
-    if state.nodes_searched % 1000 == 0 && state.is_timeout() {
-        state.timed_out = true;
-        return 0; // Return neutral score on timeout
+    // Neutral score once out of time; callers discard it
+    if !state.enter_node(ply) {
+        return 0;
     }
 
     let alpha_orig = alpha;
-    let hash = zobrist::get_zobrist().hash(board);
-
-    // TT Lookup
-    if config.use_tt {
-        if let Some(entry) = state.tt.get(hash) {
-            if entry.depth >= depth {
-                match entry.bound {
-                    Bound::Exact => return entry.score,
-                    Bound::Lower => alpha = alpha.max(entry.score),
-                    Bound::Upper => {
-                        if entry.score < beta {
-                            return entry.score;
-                        }
-                    }
-                }
-                if alpha >= beta {
-                    return entry.score;
-                }
+    let hash = board.hash;
+
+    if let Some(score) = goal_score(board, ply) {
+        return score;
+    }
+
+    if is_draw(board, hash, state) {
+        return 0;
+    }
+
+    // Out of room for the per-ply tables (only reachable through extensions)
+    if ply >= MAX_PLY - 1 {
+        return static_eval(board, player, config);
+    }
+
+    // Set while searching the alternatives to a possibly singular TT move
+    let excluded = state.stack[ply].excluded;
+    let in_check = crate::moves::is_in_check(board, player);
+
+    // Check extension: a check is answered one ply deeper
+    if in_check && config.extensions.check && excluded.is_none() && state.can_extend(ply, config) {
+        depth += 1;
+        state.stack[ply].extensions += 1;
+    }
+
+    // TT Lookup (the excluded-move search shares the hash but not the result)
+    let tt_entry = if config.use_tt && excluded.is_none() {
+        state.tt.get(hash)
+    } else {
+        None
+    };
+    if let Some(entry) = tt_entry {
+        let score = score_from_tt(entry.score, ply);
+
+        // A bound proving a mate holds whatever depth it was found at
+        let proven_mate = match entry.bound {
+            Bound::Lower => score >= MATE_BOUND,
+            Bound::Upper => score <= -MATE_BOUND,
+            Bound::Exact => false,
+        };
+
+        if entry.depth >= depth || proven_mate {
+            match entry.bound {
+                Bound::Exact => return score,
+                Bound::Lower if score >= beta => return score,
+                Bound::Upper if score <= alpha => return score,
+                _ => {}
             }
         }
     }
 
+    // Forward pruning needs a static evaluation that means something: not
+    // in check, not while proving a mate, and not on the principal variation
+    let eval = if in_check {
+        -INFINITY
+    } else {
+        static_eval(board, player, config)
+    };
+    let margins = board.rules.pruning;
+    let can_prune = config.forward_pruning
+        && !in_check
+        && ply > 0
+        && excluded.is_none()
+        && alpha.abs() < MATE_BOUND
+        && beta.abs() < MATE_BOUND;
+    let is_pv = beta - alpha > 1;
+
+    // Reverse futility: so far above beta that a quiet move will not lose it
+    if can_prune
+        && !is_pv
+        && margins.reverse_futility > 0
+        && depth <= REVERSE_FUTILITY_MAX_DEPTH
+        && eval - margins.reverse_futility * depth as i32 >= beta
+    {
+        return eval;
+    }
+
+    // Razoring: so far below alpha that only captures could help
+    if can_prune
+        && !is_pv
+        && margins.razoring > 0
+        && depth <= RAZORING_MAX_DEPTH
+        && eval + margins.razoring * depth as i32 <= alpha
+    {
+        let score = quiescence(board, alpha, alpha + 1, player, 0, ply, config, state);
+        if score <= alpha {
+            return score;
+        }
+    }
+
     // Null Move Pruning (NMP)
     // Skip if we're in check, at low depth, or in a zugzwang-prone position
-    let in_check = crate::moves::is_in_check(board, player);
-    if !in_check && depth >= 3 && ply > 0 {
-        // Static evaluation
-        let eval = evaluate(board, config);
-        let eval_player = if player == board.current_player {
-            eval
-        } else {
-            -eval
-        };
-
+    if !in_check && depth >= 3 && ply > 0 && excluded.is_none() {
         // Only try NMP if we're doing well (above beta)
-        if eval_player >= beta {
+        if eval >= beta {
             // Make a null move (pass turn to opponent)
             let mut null_board = board.clone();
-            null_board.current_player = 3 - null_board.current_player;
+            null_board.make_null_move();
+            state.enter_child(ply, None, false, 0);
 
             // Reduced depth search (R = 2)
             let reduction = 2;
@@ -254,6 +741,7 @@ fn alpha_beta(
                 0
             };
 
+            let saved_start = state.enter_null_move(hash);
             let null_score = -alpha_beta(
                 &null_board,
                 null_depth,
@@ -264,6 +752,7 @@ fn alpha_beta(
                 config,
                 state,
             );
+            state.leave_null_move(saved_start);
 
             // If null move fails high, we can prune this branch
             if null_score >= beta {
@@ -274,75 +763,186 @@ fn alpha_beta(
 
     // Leaf node - quiescence search
     if depth == 0 {
-        return quiescence(board, alpha, beta, player, 0, config, state);
+        return quiescence(board, alpha, beta, player, 0, ply, config, state);
     }
 
-    let mut moves = generate_moves(board);
+    // Singular extension: when every other move falls well short of the TT
+    // move's score, the TT move is searched one ply deeper
+    let mut singular_move = None;
+    if let Some(entry) = tt_entry {
+        let tt_score = score_from_tt(entry.score, ply);
+        let candidate = config.extensions.singular
+            && depth >= SINGULAR_MIN_DEPTH
+            && entry.depth.saturating_add(3) >= depth
+            && entry.bound != Bound::Upper
+            && tt_score.abs() < MATE_BOUND
+            && state.can_extend(ply, config);
+        if let Some(tt_move) = entry.best_move.filter(|_| candidate) {
+            let singular_beta = tt_score - SINGULAR_MARGIN * depth as i32;
+            state.stack[ply].excluded = Some(tt_move);
+            let score = alpha_beta(
+                board,
+                (depth - 1) / 2,
+                singular_beta - 1,
+                singular_beta,
+                player,
+                ply,
+                config,
+                state,
+            );
+            state.stack[ply].excluded = None;
+            // The excluded search's line is not this node's
+            state.clear_pv(ply);
+            if score < singular_beta {
+                singular_move = Some(tt_move);
+            }
+        }
+    }
+
+    let (mut moves, mut scores) = state.take_buffers(ply);
+    generate_moves_into(board, &mut moves);
 
     if moves.is_empty() {
         // Checkmate or stalemate
-        return -MATE_SCORE + (ply as i32);
+        state.return_buffers(ply, moves, scores);
+        return no_moves_score(board, in_check, ply);
     }
 
     // Get TT move for ordering
     let tt_move = if config.use_tt {
-        state.tt.get(hash).and_then(|e| e.best_move.clone())
+        state.tt.get(hash).and_then(|e| e.best_move)
     } else {
         None
     };
 
     // Move ordering
-    order_moves(board, &mut moves, tt_move.as_ref(), ply, config, state);
+    score_moves(
+        board,
+        &moves,
+        &mut scores,
+        tt_move.as_ref(),
+        ply,
+        config,
+        state,
+    );
 
     let mut best_score = -INFINITY;
     let mut best_move = None;
     let mut moves_searched = 0;
+    let mut quiets_searched = 0;
+
+    // Quiet moves this far below alpha are not worth searching
+    let futile = can_prune
+        && margins.futility > 0
+        && depth <= FUTILITY_MAX_DEPTH
+        && eval + margins.futility * depth as i32 <= alpha;
+    let late_move_limit = if can_prune && margins.late_moves > 0 && depth <= LATE_MOVE_MAX_DEPTH {
+        margins.late_moves * (depth as usize).pow(2)
+    } else {
+        usize::MAX
+    };
 
-    for (_idx, m) in moves.iter().enumerate() {
+    state.path.push(hash);
+    for i in 0..moves.len() {
+        let m = pick_move(&mut moves, &mut scores, i);
+        // Picked scores are spent; mark the move searched once it is
+        scores[i] = UNSEARCHED;
+        if Some(m) == excluded {
+            continue;
+        }
         let mut new_board = board.clone();
-        let captured = new_board.get(m.to).map(|p| p.piece_type);
+        let captured = new_board.get(m.to()).copied();
 
-        if new_board.make_move(m).is_err() {
+        if new_board.make_move(&m).is_err() {
             continue;
         }
 
         let mut score;
         let is_capture = captured.is_some();
-        let is_promotion = m.promotion;
-
-        // Late Move Reduction (LMR)
-        // Apply LMR to moves that are likely less important
-        let apply_lmr =
-            depth >= 3 && moves_searched >= 4 && !is_capture && !is_promotion && !in_check;
-
-        if apply_lmr {
-            // Calculate reduction amount
-            let mut reduction = 1;
-            if depth > 6 {
-                reduction += 1;
+        let is_promotion = m.is_promotion();
+
+        // Prune quiet moves once one move has a score, but never checks
+        if !is_capture && !is_promotion {
+            if moves_searched > 0
+                && (futile || quiets_searched >= late_move_limit)
+                && !crate::moves::is_in_check(&new_board, new_board.current_player())
+            {
+                continue;
             }
-            if moves_searched > 12 {
-                reduction += 1;
+            quiets_searched += 1;
+        }
+
+        // Move extensions (at most one ply per move)
+        let previous = state.stack[ply.saturating_sub(1)];
+        let is_recapture = ply > 0
+            && is_capture
+            && previous.captured
+            && previous.played.map(|p| p.target_square()) == Some(m.target_square());
+        let extend = Some(m) == singular_move
+            || (config.extensions.promotion && is_promotion)
+            || (config.extensions.recapture && is_recapture);
+        let extension = u8::from(extend && state.can_extend(ply, config));
+        let new_depth = depth - 1 + extension;
+        state.enter_child(ply, Some(m), is_capture, extension);
+
+        if moves_searched == 0 {
+            // Principal variation: full window
+            score = -alpha_beta(
+                &new_board,
+                new_depth,
+                -beta,
+                -alpha,
+                3 - player,
+                ply + 1,
+                config,
+                state,
+            );
+        } else {
+            // Late Move Reduction (LMR)
+            // Apply LMR to moves that are likely less important
+            let apply_lmr = depth >= 3 && !is_capture && !is_promotion && !in_check;
+            let mut reduction = 0;
+            if apply_lmr && moves_searched >= 4 {
+                reduction = 1;
+                if depth > 6 {
+                    reduction += 1;
+                }
+                if moves_searched > 12 {
+                    reduction += 1;
+                }
             }
 
-            // Search with reduced depth
-            let reduced_depth = depth.saturating_sub(1 + reduction);
+            // Later moves only have to be shown no better than alpha (null window)
             score = -alpha_beta(
                 &new_board,
-                reduced_depth,
+                new_depth.saturating_sub(reduction),
                 -alpha - 1,
-                -alpha, // Null window
+                -alpha,
                 3 - player,
                 ply + 1,
                 config,
                 state,
             );
 
-            // If reduced search fails high, re-search at full depth
-            if score > alpha {
+            // If the reduced search fails high, verify at full depth
+            if score > alpha && reduction > 0 {
+                score = -alpha_beta(
+                    &new_board,
+                    new_depth,
+                    -alpha - 1,
+                    -alpha,
+                    3 - player,
+                    ply + 1,
+                    config,
+                    state,
+                );
+            }
+
+            // Better than alpha: re-search with the full window for the score
+            if score > alpha && score < beta {
                 score = -alpha_beta(
                     &new_board,
-                    depth - 1,
+                    new_depth,
                     -beta,
                     -alpha,
                     3 - player,
@@ -351,65 +951,60 @@ fn alpha_beta(
                     state,
                 );
             }
-        } else {
-            // Normal full-depth search
-            score = -alpha_beta(
-                &new_board,
-                depth - 1,
-                -beta,
-                -alpha,
-                3 - player,
-                ply + 1,
-                config,
-                state,
-            );
         }
 
-        new_board.unmake_move(m, captured).ok();
+        new_board.unmake_move(&m, captured).ok();
 
+        // Scores after a timeout are meaningless; keep them out of the tables
+        if state.timed_out {
+            break;
+        }
+
+        scores[i] = 0;
         moves_searched += 1;
 
         if score > best_score {
             best_score = score;
-            best_move = Some(m.clone());
+            best_move = Some(m);
+        }
+
+        if score > alpha {
+            state.update_pv(ply, m);
         }
 
         alpha = alpha.max(best_score);
 
         if alpha >= beta {
-            // Beta cutoff - update history and killer moves
-            let from_idx = m.from.row * 8 + m.from.col;
-            let to_idx = m.to.row * 8 + m.to.col;
-
-            // Update history heuristic (bonus based on depth squared)
-            let bonus = (depth as i32) * (depth as i32);
-            state.history[from_idx][to_idx] += bonus;
-
-            // Decay history scores to prevent overflow and favor recent history
-            if state.history[from_idx][to_idx] > 10000 {
-                for row in state.history.iter_mut() {
-                    for val in row.iter_mut() {
-                        *val /= 2;
-                    }
-                }
-            }
-
-            if config.use_killers && ply < MAX_PLY {
-                let is_capture = captured.is_some();
-                if !is_capture {
-                    // Update killer moves
-                    if state.killer_moves[ply][0].as_ref() != Some(m) {
-                        state.killer_moves[ply][1] = state.killer_moves[ply][0].clone();
-                        state.killer_moves[ply][0] = Some(m.clone());
-                    }
-                }
+            // Beta cutoff - reward the move, punish the quiet moves tried
+            // before it, and update killer moves
+            let is_capture = captured.is_some();
+            let previous = state.previous_move(ply);
+            let failed = moves[..i]
+                .iter()
+                .zip(&scores[..i])
+                .filter(|&(&q, &score)| score != UNSEARCHED && board.get(q.to()).is_none())
+                .map(|(&q, _)| q);
+            state
+                .history
+                .update(player, m, !is_capture, failed, previous, depth);
+
+            // Update killer moves
+            if config.use_killers
+                && ply < MAX_PLY
+                && !is_capture
+                && state.killer_moves[ply][0] != Some(m)
+            {
+                state.killer_moves[ply][1] = state.killer_moves[ply][0];
+                state.killer_moves[ply][0] = Some(m);
             }
             break;
         }
     }
+    state.path.pop();
+    state.return_buffers(ply, moves, scores);
 
     // Store in TT
-    if config.use_tt {
+    if config.use_tt && excluded.is_none() && !state.timed_out {
         let bound = if best_score <= alpha_orig {
             Bound::Upper
         } else if best_score >= beta {
@@ -418,67 +1013,123 @@ fn alpha_beta(
             Bound::Exact
         };
 
-        state.tt.store(hash, depth, best_score, bound, best_move);
+        let tt_score = score_to_tt(best_score, ply);
+        state.tt.store(hash, depth, tt_score, bound, best_move);
     }
 
     best_score
 }
 
 /// Quiescence search to avoid horizon effect
+#[allow(clippy::too_many_arguments)]
 fn quiescence(
     board: &Board,
     mut alpha: i32,
     beta: i32,
     player: Player,
     depth: u8,
+    ply: usize,
     config: &AIConfig,
     state: &mut SearchState,
 ) -> i32 {
-    state.nodes_searched += 1;
-
-    // Stand-pat evaluation
-    let stand_pat = evaluate(board, config);
-    let stand_pat_player = if player == board.current_player {
-        stand_pat
-    } else {
-        -stand_pat
-    };
+    if !state.enter_node(ply) {
+        return 0;
+    }
 
-    if stand_pat_player >= beta {
-        return beta;
+    if let Some(score) = goal_score(board, ply) {
+        return score;
     }
 
-    if stand_pat_player > alpha {
-        alpha = stand_pat_player;
+    // Stand-pat evaluation; in check every evasion has to be searched instead
+    let in_check = crate::moves::is_in_check(board, player);
+    let stand_pat_player = static_eval(board, player, config);
+
+    if !in_check {
+        if stand_pat_player >= beta {
+            return beta;
+        }
+
+        if stand_pat_player > alpha {
+            alpha = stand_pat_player;
+        }
     }
 
     // Depth limit
     if depth >= config.qsearch_depth {
-        return alpha;
+        return stand_pat_player.clamp(alpha, beta);
     }
 
-    let moves = generate_moves(board);
+    let (mut moves, mut scores) = state.take_buffers(ply);
+    generate_moves_into(board, &mut moves);
+
+    if in_check && moves.is_empty() {
+        state.return_buffers(ply, moves, scores);
+        return no_moves_score(board, true, ply);
+    }
+
+    // Out of check: captures, plus promotions and possible checks at the
+    // first plies; best exchanges first
+    let with_checks = depth < QSEARCH_CHECK_PLIES;
+    if !in_check {
+        moves.retain(|&m| {
+            board.get(m.to()).is_some()
+                || (with_checks
+                    && (m.is_promotion()
+                        || m.promote_to().is_some()
+                        || crate::moves::may_give_check(board, m)))
+        });
+    }
+    scores.clear();
+    scores.extend(moves.iter().map(|&m| see(board, m)));
+
+    // Delta pruning: skip captures that cannot bring the score near alpha
+    let delta = board.rules.pruning.delta;
+    let can_prune = config.forward_pruning && delta > 0 && alpha.abs() < MATE_BOUND && !in_check;
+
+    for index in 0..moves.len() {
+        let m = pick_move(&mut moves, &mut scores, index);
 
-    // Only consider captures
-    let capture_moves: Vec<_> = moves
-        .into_iter()
-        .filter(|m| board.get(m.to).is_some())
-        .collect();
+        // Captures that lose the exchange cannot raise the stand-pat score,
+        // and every capture after this one loses at least as much
+        if !in_check && scores[index] < 0 {
+            break;
+        }
 
-    for m in capture_moves {
         let mut new_board = board.clone();
-        let captured = new_board.get(m.to).map(|p| p.piece_type);
+        let captured = new_board.get(m.to()).copied();
+        let is_promotion = m.is_promotion() || m.promote_to().is_some();
+
+        // Royal captures decide the game rather than the material balance
+        if can_prune {
+            if let Some(victim) = captured {
+                if !board.rules.is_royal(victim.piece_type)
+                    && stand_pat_player + capture_gain(board, m, &victim) + delta <= alpha
+                {
+                    continue;
+                }
+            }
+        }
 
         if new_board.make_move(&m).is_err() {
             continue;
         }
 
+        // Quiet moves other than promotions only count when they give check
+        if !in_check
+            && captured.is_none()
+            && !is_promotion
+            && !crate::moves::is_in_check(&new_board, new_board.current_player())
+        {
+            continue;
+        }
+
         let score = -quiescence(
             &new_board,
             -beta,
             -alpha,
             3 - player,
             depth + 1,
+            ply + 1,
             config,
             state,
         );
@@ -486,79 +1137,239 @@ fn quiescence(
         new_board.unmake_move(&m, captured).ok();
 
         if score >= beta {
-            return beta;
+            alpha = beta;
+            break;
         }
 
         if score > alpha {
             alpha = score;
         }
     }
+    state.return_buffers(ply, moves, scores);
 
     alpha
 }
 
-/// Move ordering heuristic
-fn order_moves(
+/// Static evaluation from `player`'s point of view
+fn static_eval(board: &Board, player: Player, config: &AIConfig) -> i32 {
+    let eval = evaluate(board, config);
+    if player == board.current_player() {
+        eval
+    } else {
+        -eval
+    }
+}
+
+/// Convert a mate score from distance-to-root to distance-to-node for the TT
+fn score_to_tt(score: i32, ply: usize) -> i32 {
+    if score >= MATE_BOUND {
+        score + ply as i32
+    } else if score <= -MATE_BOUND {
+        score - ply as i32
+    } else {
+        score
+    }
+}
+
+/// Convert a TT mate score back to distance-to-root at `ply`
+fn score_from_tt(score: i32, ply: usize) -> i32 {
+    if score >= MATE_BOUND {
+        score - ply as i32
+    } else if score <= -MATE_BOUND {
+        score + ply as i32
+    } else {
+        score
+    }
+}
+
+/// Whether the draw rules end the game at this node
+fn is_draw(board: &Board, hash: u64, state: &SearchState) -> bool {
+    let draw = &board.rules.draw;
+    if draw.move_limit > 0 && board.move_count >= draw.move_limit {
+        return true;
+    }
+
+    // Same side to move: every second ancestor, starting with the grandparent,
+    // back to the last null move
+    draw.repetition
+        && state.path[state.path_start..]
+            .iter()
+            .rev()
+            .skip(1)
+            .step_by(2)
+            .any(|&h| h == hash)
+}
+
+/// Score for the side to move if a player has already reached a win condition
+fn goal_score(board: &Board, ply: usize) -> Option<i32> {
+    let winner = goal_winner(board)?;
+    Some(if winner == board.current_player() {
+        MATE_SCORE - (ply as i32)
+    } else {
+        -MATE_SCORE + (ply as i32)
+    })
+}
+
+/// Score for the side to move when it has no legal moves
+fn no_moves_score(board: &Board, in_check: bool, ply: usize) -> i32 {
+    let outcome = if in_check {
+        StalemateOutcome::Loss
+    } else {
+        board.rules.stalemate
+    };
+
+    match outcome {
+        StalemateOutcome::Loss => -MATE_SCORE + (ply as i32),
+        StalemateOutcome::Draw => 0,
+        StalemateOutcome::Win => MATE_SCORE - (ply as i32),
+    }
+}
+
+/// Score moves for ordering into `scores`; higher scores are searched first
+fn score_moves(
     board: &Board,
-    moves: &mut [Move],
+    moves: &[Move],
+    scores: &mut Vec<i32>,
     tt_move: Option<&Move>,
     ply: usize,
     config: &AIConfig,
     state: &SearchState,
 ) {
     let killers = if config.use_killers && ply < MAX_PLY {
-        state.killer_moves[ply].clone()
+        state.killer_moves[ply]
     } else {
         [None, None]
     };
+    let player = board.current_player();
+    let previous = state.previous_move(ply);
+    let counter_move = if config.use_killers {
+        state.history.counter_move(player, previous)
+    } else {
+        None
+    };
 
-    moves.sort_by_key(|m| {
+    scores.clear();
+    scores.extend(moves.iter().map(|m| {
         let mut score = 0;
 
         // TT move first
         if Some(m) == tt_move {
-            score -= 30000;
+            score += 1_000_000;
         }
 
-        // Captures (MVV-LVA)
-        if let Some(victim) = board.get(m.to) {
-            let victim_value = piece_value(&victim.piece_type);
-            score -= 10000 + victim_value;
-        }
+        // Captures (MVV), with exchanges that lose material after quiet moves
+        if let Some(victim) = board.get(m.to()) {
+            score += 200_000 + exchange_value(&board.rules, victim);
+            if Some(m) != tt_move && see(board, *m) < 0 {
+                score -= 400_000;
+            }
+        } else {
+            // Killer moves, then the reply that last refuted the opponent's move
+            if Some(*m) == killers[0] {
+                score += 100_000;
+            } else if Some(*m) == killers[1] {
+                score += 90_000;
+            } else if Some(*m) == counter_move {
+                score += 80_000;
+            }
 
-        // Killer moves
-        if Some(m) == killers[0].as_ref() {
-            score -= 5000;
-        } else if Some(m) == killers[1].as_ref() {
-            score -= 4000;
+            // History heuristic - prioritize moves that have been good before
+            score += state.history.score(player, *m, previous);
         }
 
-        // History heuristic - prioritize moves that have been good before
-        let from_idx = m.from.row * 8 + m.from.col;
-        let to_idx = m.to.row * 8 + m.to.col;
-        let history_score = state.history[from_idx][to_idx];
-        score -= history_score;
-
         // Promotions
-        if m.promoted {
-            score -= 3000;
+        if m.is_promotion() || m.promote_to().is_some() {
+            score += 3000;
         }
 
         score
-    });
+    }));
 }
 
-/// Simple piece value for move ordering
-fn piece_value(piece_type: &PieceType) -> i32 {
-    match piece_type {
-        PieceType::King | PieceType::ChessKing => 20000,
-        PieceType::ChessQueen => 950,
-        PieceType::Rook | PieceType::ChessRook => 500,
-        PieceType::Bishop | PieceType::ChessBishop => 330,
-        PieceType::Gold => 600,
-        PieceType::Silver => 500,
-        PieceType::Knight | PieceType::ChessKnight => 320,
-        PieceType::Lance => 300,
-        PieceType::Pawn | PieceType::ChessPawn => 100,
+/// Move the best-scored move from `index` onwards to `index` and return it
+/// (one step of a selection sort, so cutoffs skip sorting the rest)
+fn pick_move(moves: &mut [Move], scores: &mut [i32], index: usize) -> Move {
+    let mut best = index;
+    for i in index + 1..moves.len() {
+        if scores[i] > scores[best] {
+            best = i;
+        }
+    }
+    moves.swap(index, best);
+    scores.swap(index, best);
+    moves[index]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bench;
+    use std::rc::Rc;
+
+    #[test]
+    fn pv_continues_past_tt_cutoffs() {
+        let rows = [
+            ". . . . . . . .",
+            ". . . . . . . .",
+            ". . . . . . . .",
+            ". . . ck . . . .",
+            ". . . . . . . .",
+            ". . . . . . . .",
+            ". . . . . . . .",
+            "CK . . . . . . CR",
+        ];
+        let rules = r#"{"hands": {"player1": false, "player2": false}}"#;
+        let board = bench::start_board(&rows, Some(rules)).unwrap();
+
+        let mut config = AIConfig::from_level(6);
+        config.max_depth = 6;
+        config.timeout_ms = u32::MAX;
+        let mut tt = TranspositionTable::new(config.tt_size_mb);
+        let mut history = History::new(board.rows(), board.cols());
+        let mut search = || find_best_move(&board, 1, &config, &mut tt, &mut history, &mut |_| {});
+
+        // The second search finds the PV children in the table and cuts off
+        search().unwrap();
+        let pv = search().unwrap().lines.swap_remove(0).pv;
+        assert!(pv.len() > 1, "PV is {} moves long", pv.len());
+
+        // Every move is legal where it is played
+        let mut board = board;
+        for m in &pv {
+            assert!(generate_moves(&board).contains(m));
+            board.make_move(m).unwrap();
+        }
+    }
+
+    #[test]
+    fn null_move_starts_a_new_repetition_window() {
+        let mut board = bench::position("shogi");
+        let mut rules = (*board.rules).clone();
+        rules.draw.repetition = true;
+        board.rules = Rc::new(rules);
+
+        let config = AIConfig::from_level(6);
+        let mut tt = TranspositionTable::new(1);
+        let mut history = History::new(board.rows(), board.cols());
+        let mut on_info = |_: &SearchInfo| {};
+        let mut state = SearchState::new(&config, &mut tt, &mut history, &mut on_info);
+
+        // Root a, its child b, then b passes the turn
+        let (a, b, c, d) = (1, 2, 3, 4);
+        state.path.extend([a, b]);
+        let saved = state.enter_null_move(b);
+
+        // Same hash as the root, but with the other side to move
+        assert!(!is_draw(&board, a, &state));
+
+        // c and d below the null move, then c again: a real repetition
+        state.path.extend([c, d]);
+        assert!(is_draw(&board, c, &state));
+        assert!(!is_draw(&board, d, &state));
+        state.path.truncate(state.path.len() - 2);
+
+        state.leave_null_move(saved);
+        assert_eq!(state.path, [a, b]);
+        assert!(is_draw(&board, a, &state));
     }
 }
@@ -0,0 +1,83 @@
+//! Static exchange evaluation: the material balance of the capture sequence
+//! on one square, with both sides recapturing least valuable piece first
+
+use crate::bitboard::{BitPosition, Bitboard, Square};
+use crate::board::Board;
+use crate::eval::exchange_value;
+use crate::moves::landed_piece;
+use crate::rules::RuleSet;
+use crate::types::*;
+
+/// Longest exchange followed; more attackers than this never meet on a square
+const MAX_EXCHANGE: usize = 32;
+
+/// Material `m` wins for the side to move if every capture on its target
+/// square that pays off is played
+///
+/// Sliders hidden behind a piece that captures join the exchange, and
+/// promoted pieces are moved and valued as their promoted side. Pins and
+/// promotions on recaptures are not considered.
+pub fn see(board: &Board, m: Move) -> i32 {
+    let bits = &board.bits;
+    let rules = &board.rules;
+    let to = m.target_square();
+
+    let moving = Piece {
+        piece_type: m.piece_type(),
+        player: board.current_player(),
+        promoted: m.was_promoted(),
+    };
+    let landed = landed_piece(board, m);
+
+    let mut occupied = bits.occupied();
+    let mut gain = [0; MAX_EXCHANGE];
+    gain[0] = board
+        .get(m.to())
+        .map_or(0, |victim| exchange_value(rules, victim));
+    if !m.is_drop() {
+        gain[0] += exchange_value(rules, &landed) - exchange_value(rules, &moving);
+        occupied.toggle(m.origin_square());
+    }
+
+    // Value of the piece the next capture takes
+    let mut on_square = exchange_value(rules, &landed);
+    let mut side = 3 - board.current_player();
+    let mut depth = 0;
+
+    while depth + 1 < MAX_EXCHANGE {
+        let attackers = bits.attackers_with(to, side, rules, occupied);
+        let Some((from, attacker_value)) = least_valuable(bits, rules, attackers) else {
+            break;
+        };
+
+        depth += 1;
+        gain[depth] = on_square - gain[depth - 1];
+        // Neither side can improve on stopping here
+        if (-gain[depth - 1]).max(gain[depth]) < 0 {
+            break;
+        }
+
+        on_square = attacker_value;
+        occupied.toggle(from);
+        side = 3 - side;
+    }
+
+    // Each side may stop the exchange instead of recapturing
+    while depth > 0 {
+        gain[depth - 1] = -(-gain[depth - 1]).max(gain[depth]);
+        depth -= 1;
+    }
+    gain[0]
+}
+
+/// Square and value of the cheapest piece in `attackers`
+fn least_valuable(
+    bits: &BitPosition,
+    rules: &RuleSet,
+    attackers: Bitboard,
+) -> Option<(Square, i32)> {
+    attackers
+        .squares()
+        .filter_map(|sq| Some((sq, exchange_value(rules, &bits.piece_at(sq)?))))
+        .min_by_key(|&(_, value)| value)
+}
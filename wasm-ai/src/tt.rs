@@ -1,67 +1,199 @@
+use crate::search::MATE_SCORE;
 use crate::types::Move;
+use crate::zobrist::{get_zobrist, KEY_SEED};
+use serde::Serialize;
 
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
 pub enum Bound {
     Exact,
     Lower,
     Upper,
 }
 
-#[derive(Clone, Debug)]
+/// Result of a table probe
+#[derive(Clone, Copy, Debug)]
 pub struct TTEntry {
-    pub hash: u64,
     pub depth: u8,
     pub score: i32,
     pub bound: Bound,
     pub best_move: Option<Move>,
 }
 
+/// Largest hash size accepted by `set_hash_mb`
+pub const MAX_HASH_MB: usize = 2048;
+
+/// Magic bytes opening an exported table
+const EXPORT_MAGIC: &[u8; 4] = b"HBTT";
+
+/// Bumped whenever the export layout changes
+const EXPORT_VERSION: u32 = 1;
+
+/// Magic, version, key seed, key fingerprint, variant, size, generation, count
+const EXPORT_HEADER_LEN: usize = 4 + 4 + 8 + 8 + 8 + 4 + 1 + 4;
+
+/// Bucket, key, move, score, depth, generation and bound
+const EXPORT_RECORD_LEN: usize = 4 + 4 + 4 + 4 + 1 + 1;
+
+/// Entries per bucket (one 64-byte cache line)
+const BUCKET_SIZE: usize = 4;
+
+/// Generations wrap within the 6 bits left next to the bound
+const GENERATION_MASK: u8 = 0x3f;
+
+/// Stored entry, 16 bytes
+#[derive(Clone, Copy, Default)]
+struct Slot {
+    key: u32, // Upper half of the hash, verifies the match
+    best_move: Option<Move>,
+    score: i32,
+    depth: u8,
+    gen_bound: u8, // generation << 2 | bound (0 = empty slot)
+}
+
+impl Slot {
+    fn is_empty(&self) -> bool {
+        self.gen_bound & 0x3 == 0
+    }
+
+    fn generation(&self) -> u8 {
+        self.gen_bound >> 2
+    }
+
+    fn bound(&self) -> Bound {
+        match self.gen_bound & 0x3 {
+            1 => Bound::Exact,
+            2 => Bound::Lower,
+            _ => Bound::Upper,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Default)]
+#[repr(align(64))]
+struct Bucket {
+    slots: [Slot; BUCKET_SIZE],
+}
+
+/// Transposition table, kept across searches
+///
+/// Each search bumps the generation; entries from older searches are
+/// replaced before fresh ones.
 pub struct TranspositionTable {
-    entries: Vec<Option<TTEntry>>,
-    size: usize,
+    buckets: Vec<Bucket>,
+    size_mb: usize,
+    generation: u8,
+    variant: u64, // Fingerprint of the rules the entries were searched under
 }
 
 impl TranspositionTable {
     /// Create a new transposition table
-    /// size_mb: size in megabytes
+    /// size_mb: size in megabytes (falls back to no table if memory runs out)
     pub fn new(size_mb: usize) -> Self {
-        if size_mb == 0 {
-            return TranspositionTable {
-                entries: Vec::new(),
-                size: 0,
-            };
-        }
+        Self::try_new(size_mb).unwrap_or_else(|_| Self::disabled())
+    }
+
+    /// Create a table, reporting an error instead of aborting when the
+    /// memory cannot be allocated
+    pub fn try_new(size_mb: usize) -> Result<Self, String> {
+        let too_big = || format!("Not enough memory for a {} MB hash table", size_mb);
+        let bytes = size_mb.checked_mul(1024 * 1024).ok_or_else(too_big)?;
+        let num_buckets = bytes / std::mem::size_of::<Bucket>();
 
-        // Calculate number of entries based on size
-        let entry_size = std::mem::size_of::<Option<TTEntry>>();
-        let num_entries = (size_mb * 1024 * 1024) / entry_size;
+        let mut buckets = Vec::new();
+        buckets
+            .try_reserve_exact(num_buckets)
+            .map_err(|_| too_big())?;
+        buckets.resize(num_buckets, Bucket::default());
 
+        Ok(TranspositionTable {
+            buckets,
+            size_mb,
+            generation: 0,
+            variant: 0,
+        })
+    }
+
+    fn disabled() -> Self {
         TranspositionTable {
-            entries: vec![None; num_entries],
-            size: num_entries,
+            buckets: Vec::new(),
+            size_mb: 0,
+            generation: 0,
+            variant: 0,
         }
     }
 
+    /// Replace the table with an empty one of `size_mb`
+    ///
+    /// The new table is allocated while the old one is still alive, so on
+    /// failure the old table and its entries are left as they were.
+    pub fn resize(&mut self, size_mb: usize) -> Result<(), String> {
+        if size_mb > max_hash_mb() {
+            return Err(format!(
+                "Hash size {} MB is more than the {} MB available",
+                size_mb,
+                max_hash_mb()
+            ));
+        }
+
+        let mut table = Self::try_new(size_mb)?;
+        table.variant = self.variant;
+        *self = table;
+        Ok(())
+    }
+
+    /// Bytes held by the table
+    pub fn memory_bytes(&self) -> usize {
+        self.buckets.len() * std::mem::size_of::<Bucket>()
+    }
+
     pub fn is_enabled(&self) -> bool {
-        self.size > 0
+        !self.buckets.is_empty()
+    }
+
+    pub fn size_mb(&self) -> usize {
+        self.size_mb
+    }
+
+    /// Use the table for positions under rules with fingerprint `variant`
+    /// (see `RuleSet::fingerprint`), dropping entries searched under others
+    pub fn set_variant(&mut self, variant: u64) {
+        if variant != self.variant {
+            self.clear();
+            self.variant = variant;
+        }
+    }
+
+    /// Start a new search; entries stored so far become stale
+    pub fn new_search(&mut self) {
+        self.generation = (self.generation + 1) & GENERATION_MASK;
     }
 
-    fn get_index(&self, hash: u64) -> usize {
-        (hash as usize) % self.size
+    /// Bucket from the lower half of the hash (the upper half is the key)
+    fn bucket_index(&self, hash: u64) -> usize {
+        ((hash as u32 as u64 * self.buckets.len() as u64) >> 32) as usize
     }
 
-    pub fn get(&self, hash: u64) -> Option<&TTEntry> {
+    fn key(hash: u64) -> u32 {
+        (hash >> 32) as u32
+    }
+
+    pub fn get(&self, hash: u64) -> Option<TTEntry> {
         if !self.is_enabled() {
             return None;
         }
 
-        let idx = self.get_index(hash);
-        if let Some(ref entry) = self.entries[idx] {
-            if entry.hash == hash {
-                return Some(entry);
-            }
-        }
-        None
+        let key = Self::key(hash);
+        self.buckets[self.bucket_index(hash)]
+            .slots
+            .iter()
+            .find(|slot| !slot.is_empty() && slot.key == key)
+            .map(|slot| TTEntry {
+                depth: slot.depth,
+                score: slot.score,
+                bound: slot.bound(),
+                best_move: slot.best_move,
+            })
     }
 
     pub fn store(
@@ -76,28 +208,344 @@ impl TranspositionTable {
             return;
         }
 
-        let idx = self.get_index(hash);
+        let key = Self::key(hash);
+        let generation = self.generation;
+        let idx = self.bucket_index(hash);
+        let slots = &mut self.buckets[idx].slots;
+
+        // Same position: overwrite unless a deeper bound from this search is
+        // there (always overwriting probed fewer hits and searched more nodes)
+        if let Some(slot) = slots
+            .iter_mut()
+            .find(|slot| !slot.is_empty() && slot.key == key)
+        {
+            if bound != Bound::Exact && depth < slot.depth && slot.generation() == generation {
+                return;
+            }
+            let best_move = best_move.or(slot.best_move);
+            *slot = Self::slot(key, depth, score, bound, best_move, generation);
+            return;
+        }
+
+        // Otherwise replace an empty slot, or the shallowest once older entries
+        // have been discounted by their age
+        let victim = slots
+            .iter_mut()
+            .min_by_key(|slot| {
+                if slot.is_empty() {
+                    i32::MIN
+                } else {
+                    let age = generation.wrapping_sub(slot.generation()) & GENERATION_MASK;
+                    slot.depth as i32 - 8 * age as i32
+                }
+            })
+            .expect("buckets are never empty");
+        *victim = Self::slot(key, depth, score, bound, best_move, generation);
+    }
 
-        // Replacement scheme: prefer deeper searches
-        let should_replace = match &self.entries[idx] {
-            None => true,
-            Some(existing) => existing.hash != hash || depth >= existing.depth,
+    fn slot(
+        key: u32,
+        depth: u8,
+        score: i32,
+        bound: Bound,
+        best_move: Option<Move>,
+        generation: u8,
+    ) -> Slot {
+        let bound_bits = match bound {
+            Bound::Exact => 1,
+            Bound::Lower => 2,
+            Bound::Upper => 3,
         };
+        Slot {
+            key,
+            best_move,
+            score,
+            depth,
+            gen_bound: generation << 2 | bound_bits,
+        }
+    }
 
-        if should_replace {
-            self.entries[idx] = Some(TTEntry {
-                hash,
-                depth,
-                score,
-                bound,
+    /// Permille of sampled slots filled by the current search
+    pub fn hashfull(&self) -> u32 {
+        let sample = self.buckets.iter().take(1000 / BUCKET_SIZE);
+        let mut used = 0u32;
+        let mut total = 0u32;
+        for bucket in sample {
+            for slot in &bucket.slots {
+                total += 1;
+                if !slot.is_empty() && slot.generation() == self.generation {
+                    used += 1;
+                }
+            }
+        }
+        (used * 1000).checked_div(total).unwrap_or(0)
+    }
+
+    /// Serialize the filled slots behind a header naming the key seed and
+    /// the fingerprint of the rules the entries were searched under
+    pub fn export(&self) -> Vec<u8> {
+        let filled = self
+            .buckets
+            .iter()
+            .flat_map(|bucket| &bucket.slots)
+            .filter(|slot| !slot.is_empty())
+            .count();
+
+        let mut out = Vec::with_capacity(EXPORT_HEADER_LEN + filled * EXPORT_RECORD_LEN);
+        out.extend_from_slice(EXPORT_MAGIC);
+        out.extend_from_slice(&EXPORT_VERSION.to_le_bytes());
+        out.extend_from_slice(&KEY_SEED.to_le_bytes());
+        out.extend_from_slice(&get_zobrist().fingerprint().to_le_bytes());
+        out.extend_from_slice(&self.variant.to_le_bytes());
+        out.extend_from_slice(&(self.size_mb as u32).to_le_bytes());
+        out.push(self.generation);
+        out.extend_from_slice(&(filled as u32).to_le_bytes());
+
+        for (idx, bucket) in self.buckets.iter().enumerate() {
+            for slot in bucket.slots.iter().filter(|slot| !slot.is_empty()) {
+                out.extend_from_slice(&(idx as u32).to_le_bytes());
+                out.extend_from_slice(&slot.key.to_le_bytes());
+                out.extend_from_slice(&slot.best_move.map_or(0, Move::bits).to_le_bytes());
+                out.extend_from_slice(&slot.score.to_le_bytes());
+                out.push(slot.depth);
+                out.push(slot.gen_bound);
+            }
+        }
+        out
+    }
+
+    /// Load a table saved by `export`, taking over its size and rules
+    ///
+    /// The entries are read into a new table, which replaces the current one
+    /// only once all of them have been checked.
+    pub fn import(&mut self, data: &[u8]) -> Result<(), String> {
+        let corrupt = || "Hash table export is corrupt".to_string();
+        let mut reader = Reader { data, pos: 0 };
+        if &reader.take::<4>()? != EXPORT_MAGIC {
+            return Err("Not a hash table export".to_string());
+        }
+        let version = u32::from_le_bytes(reader.take()?);
+        if version != EXPORT_VERSION {
+            return Err(format!("Unsupported hash table export version {}", version));
+        }
+        let seed = u64::from_le_bytes(reader.take()?);
+        let fingerprint = u64::from_le_bytes(reader.take()?);
+        if seed != KEY_SEED || fingerprint != get_zobrist().fingerprint() {
+            return Err("Hash table was saved with different position keys".to_string());
+        }
+        let variant = u64::from_le_bytes(reader.take()?);
+        let size_mb = u32::from_le_bytes(reader.take()?) as usize;
+        let [generation] = reader.take()?;
+        let count = u32::from_le_bytes(reader.take()?) as usize;
+
+        let expected_len = count
+            .checked_mul(EXPORT_RECORD_LEN)
+            .and_then(|records| records.checked_add(EXPORT_HEADER_LEN));
+        if expected_len != Some(data.len()) {
+            return Err("Hash table export has the wrong length".to_string());
+        }
+        if generation > GENERATION_MASK {
+            return Err(corrupt());
+        }
+        // The current table stays allocated until the new one is complete
+        if size_mb > max_hash_mb() {
+            return Err(format!(
+                "Hash size {} MB is more than the {} MB available",
+                size_mb,
+                max_hash_mb()
+            ));
+        }
+
+        let mut table = Self::try_new(size_mb)?;
+        table.generation = generation;
+        table.variant = variant;
+        for _ in 0..count {
+            let bucket = u32::from_le_bytes(reader.take()?) as usize;
+            let key = u32::from_le_bytes(reader.take()?);
+            let move_bits = u32::from_le_bytes(reader.take()?);
+            let score = i32::from_le_bytes(reader.take()?);
+            let [depth] = reader.take()?;
+            let [gen_bound] = reader.take()?;
+
+            let best_move = match move_bits {
+                0 => None,
+                bits => Some(Move::from_bits(bits).ok_or_else(corrupt)?),
+            };
+            if score.abs() > MATE_SCORE || gen_bound & 0x3 == 0 {
+                return Err(corrupt());
+            }
+            let free = table
+                .buckets
+                .get_mut(bucket)
+                .and_then(|bucket| bucket.slots.iter_mut().find(|s| s.is_empty()))
+                .ok_or_else(corrupt)?;
+            *free = Slot {
+                key,
                 best_move,
-            });
+                score,
+                depth,
+                gen_bound,
+            };
         }
+
+        *self = table;
+        Ok(())
     }
 
     pub fn clear(&mut self) {
-        for entry in &mut self.entries {
-            *entry = None;
+        self.buckets.fill(Bucket::default());
+        self.generation = 0;
+    }
+}
+
+/// Largest new table that fits next to the memory already in use,
+/// including the table it will replace
+#[cfg(target_arch = "wasm32")]
+fn max_hash_mb() -> usize {
+    // 32-bit WASM memory grows to at most 65536 pages of 64 KiB (4 GiB)
+    const MAX_MEMORY: u64 = 65536 * 65536;
+    let used = core::arch::wasm32::memory_size(0) as u64 * 65536;
+    let free = MAX_MEMORY - used;
+    ((free / (1024 * 1024)) as usize).min(MAX_HASH_MB)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn max_hash_mb() -> usize {
+    MAX_HASH_MB
+}
+
+/// Cursor over exported bytes
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl Reader<'_> {
+    fn take<const N: usize>(&mut self) -> Result<[u8; N], String> {
+        let bytes = self
+            .data
+            .get(self.pos..self.pos + N)
+            .ok_or("Hash table export is truncated")?;
+        self.pos += N;
+        Ok(bytes.try_into().expect("slice has length N"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{PieceType, Position};
+
+    const HASHES: [u64; 3] = [
+        0x1234_5678_9abc_def0,
+        0x0fed_cba9_8765_4321,
+        0xdead_beef_0000_0001,
+    ];
+
+    /// 1 MB table for rules `7` holding three entries
+    fn filled_table() -> TranspositionTable {
+        let mut tt = TranspositionTable::new(1);
+        tt.set_variant(7);
+        tt.new_search();
+        let m = Move::drop(PieceType::Pawn, Position { row: 4, col: 4 });
+        tt.store(HASHES[0], 5, 120, Bound::Exact, Some(m));
+        tt.store(HASHES[1], 3, -MATE_SCORE + 4, Bound::Upper, None);
+        tt.store(HASHES[2], 9, MATE_SCORE - 2, Bound::Lower, Some(m));
+        tt
+    }
+
+    fn same_entry(a: Option<TTEntry>, b: Option<TTEntry>) -> bool {
+        match (a, b) {
+            (Some(a), Some(b)) => {
+                (a.depth, a.score, a.bound, a.best_move) == (b.depth, b.score, b.bound, b.best_move)
+            }
+            (None, None) => true,
+            _ => false,
         }
     }
+
+    /// Import `data` into a table that already holds an entry, and check it
+    /// is rejected with that entry kept
+    fn assert_rejected(data: &[u8]) {
+        let mut tt = TranspositionTable::new(2);
+        tt.store(42, 1, 10, Bound::Exact, None);
+        assert!(tt.import(data).is_err());
+        assert_eq!(tt.size_mb(), 2);
+        assert!(tt.get(42).is_some());
+    }
+
+    #[test]
+    fn export_import_round_trips() {
+        let source = filled_table();
+        let data = source.export();
+
+        let mut tt = TranspositionTable::new(2);
+        tt.import(&data).unwrap();
+        assert_eq!(tt.size_mb(), 1);
+        for hash in HASHES {
+            assert!(tt.get(hash).is_some());
+            assert!(same_entry(tt.get(hash), source.get(hash)));
+        }
+        assert_eq!(tt.export(), data);
+    }
+
+    #[test]
+    fn other_rules_drop_imported_entries() {
+        let mut tt = TranspositionTable::new(1);
+        tt.import(&filled_table().export()).unwrap();
+        tt.set_variant(7);
+        assert!(tt.get(HASHES[0]).is_some());
+        tt.set_variant(8);
+        assert!(tt.get(HASHES[0]).is_none());
+    }
+
+    #[test]
+    fn failed_resize_keeps_the_table() {
+        let mut tt = filled_table();
+        assert!(tt.resize(MAX_HASH_MB + 1).is_err());
+        assert_eq!(tt.size_mb(), 1);
+        assert!(tt.get(HASHES[0]).is_some());
+
+        tt.resize(2).unwrap();
+        assert_eq!(tt.size_mb(), 2);
+        assert!(tt.get(HASHES[0]).is_none());
+    }
+
+    #[test]
+    fn import_rejects_truncated_data() {
+        let data = filled_table().export();
+        assert_rejected(&data[..data.len() - 1]);
+        assert_rejected(&data[..EXPORT_HEADER_LEN - 1]);
+        assert_rejected(&[]);
+    }
+
+    #[test]
+    fn import_rejects_record_counts_past_the_data() {
+        let mut data = filled_table().export();
+        let count = EXPORT_HEADER_LEN - 4;
+        data[count..count + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert_rejected(&data);
+    }
+
+    #[test]
+    fn import_rejects_wrong_version() {
+        let mut data = filled_table().export();
+        data[4..8].copy_from_slice(&(EXPORT_VERSION + 1).to_le_bytes());
+        assert_rejected(&data);
+    }
+
+    #[test]
+    fn import_rejects_wrong_key_fingerprint() {
+        let mut data = filled_table().export();
+        data[16] ^= 1;
+        assert_rejected(&data);
+    }
+
+    #[test]
+    fn import_rejects_scores_past_mate() {
+        let mut data = filled_table().export();
+        let score = EXPORT_HEADER_LEN + 12;
+        data[score..score + 4].copy_from_slice(&(MATE_SCORE + 1).to_le_bytes());
+        assert_rejected(&data);
+    }
 }
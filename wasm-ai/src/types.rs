@@ -1,8 +1,13 @@
+use crate::bitboard::{position, square, Square, NUM_SQUARES};
+use crate::rules::RuleSet;
+use crate::tt::Bound;
 use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::num::NonZeroU32;
 use wasm_bindgen::JsValue;
 
 /// Piece type on the board
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub enum PieceType {
     // Shogi pieces
     #[serde(rename = "king")]
@@ -37,11 +42,80 @@ pub enum PieceType {
     ChessPawn,
 }
 
+/// How a piece moves; promoted pieces move as their promotion target
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Movement {
+    King,
+    Rook,
+    Bishop,
+    Gold,
+    Silver,
+    Knight,
+    Lance,
+    Pawn,
+    ChessQueen,
+    ChessRook,
+    ChessBishop,
+    ChessKnight,
+    ChessPawn,
+    /// Rook plus one diagonal step (promoted rook)
+    Dragon,
+    /// Bishop plus one orthogonal step (promoted bishop)
+    Horse,
+}
+
+/// Number of distinct piece types
+pub const NUM_PIECE_TYPES: usize = 14;
+
+impl PieceType {
+    pub const ALL: [PieceType; NUM_PIECE_TYPES] = [
+        PieceType::King,
+        PieceType::Rook,
+        PieceType::Bishop,
+        PieceType::Gold,
+        PieceType::Silver,
+        PieceType::Knight,
+        PieceType::Lance,
+        PieceType::Pawn,
+        PieceType::ChessKing,
+        PieceType::ChessQueen,
+        PieceType::ChessRook,
+        PieceType::ChessBishop,
+        PieceType::ChessKnight,
+        PieceType::ChessPawn,
+    ];
+
+    /// Dense index into per-type tables (position in `ALL`)
+    pub fn index(self) -> usize {
+        self as usize
+    }
+
+    /// Movement of the unpromoted piece
+    pub fn movement(self) -> Movement {
+        match self {
+            PieceType::King | PieceType::ChessKing => Movement::King,
+            PieceType::Rook => Movement::Rook,
+            PieceType::Bishop => Movement::Bishop,
+            PieceType::Gold => Movement::Gold,
+            PieceType::Silver => Movement::Silver,
+            PieceType::Knight => Movement::Knight,
+            PieceType::Lance => Movement::Lance,
+            PieceType::Pawn => Movement::Pawn,
+            PieceType::ChessQueen => Movement::ChessQueen,
+            PieceType::ChessRook => Movement::ChessRook,
+            PieceType::ChessBishop => Movement::ChessBishop,
+            PieceType::ChessKnight => Movement::ChessKnight,
+            PieceType::ChessPawn => Movement::ChessPawn,
+        }
+    }
+}
+
 /// Player (1 or 2)
 pub type Player = u8;
 
 /// Piece on the board
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Piece {
     #[serde(rename = "type")]
     pub piece_type: PieceType,
@@ -56,15 +130,149 @@ pub struct Position {
     pub col: usize,
 }
 
-/// Move representation
-#[derive(Debug, Clone, PartialEq, Eq)]
-pub struct Move {
-    pub from: Position,
-    pub to: Position,
-    pub piece_type: PieceType,
-    pub promoted: bool,
-    pub promotion: bool, // Whether this move promotes the piece
-    pub captured: Option<PieceType>,
+/// Move packed into 32 bits
+///
+/// | bits  | field                                          |
+/// |-------|------------------------------------------------|
+/// | 0-6   | destination square (`bitboard::square`)        |
+/// | 7-13  | origin square (same as destination for drops)  |
+/// | 14-17 | moving piece type                              |
+/// | 18-21 | chess-style promotion target (0 = none)        |
+/// | 22    | drop from hand                                 |
+/// | 23    | promotes (flips) the piece                     |
+/// | 24    | piece was already promoted                     |
+/// | 31    | always set, so `Option<Move>` stays 4 bytes    |
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Move(NonZeroU32);
+
+impl Move {
+    const SQUARE_MASK: u32 = 0x7f;
+    const FROM_SHIFT: u32 = 7;
+    const PIECE_SHIFT: u32 = 14;
+    const PROMOTE_TO_SHIFT: u32 = 18;
+    const TYPE_MASK: u32 = 0xf;
+    const DROP: u32 = 1 << 22;
+    const PROMOTION: u32 = 1 << 23;
+    const PROMOTED: u32 = 1 << 24;
+    const VALID: u32 = 1 << 31;
+
+    fn pack(bits: u32) -> Self {
+        Self(NonZeroU32::new(bits | Self::VALID).expect("VALID bit is set"))
+    }
+
+    /// Move of `piece` from `from` to `to`
+    pub fn new(from: Position, to: Position, piece: &Piece) -> Self {
+        let mut bits = square(to) as u32
+            | (square(from) as u32) << Self::FROM_SHIFT
+            | (piece.piece_type.index() as u32) << Self::PIECE_SHIFT;
+        if piece.promoted {
+            bits |= Self::PROMOTED;
+        }
+        Self::pack(bits)
+    }
+
+    /// Drop of a hand piece onto `to`
+    pub fn drop(piece_type: PieceType, to: Position) -> Self {
+        let sq = square(to) as u32;
+        Self::pack(
+            sq | sq << Self::FROM_SHIFT
+                | (piece_type.index() as u32) << Self::PIECE_SHIFT
+                | Self::DROP,
+        )
+    }
+
+    /// The same move, flipping the piece to its promoted side
+    pub fn with_promotion(self) -> Self {
+        Self::pack(self.0.get() | Self::PROMOTION)
+    }
+
+    /// The same move, turning the piece into `piece_type` (chess pawns)
+    pub fn with_promote_to(self, piece_type: PieceType) -> Self {
+        let bits = self.0.get() & !(Self::TYPE_MASK << Self::PROMOTE_TO_SHIFT);
+        Self::pack(bits | (piece_type.index() as u32 + 1) << Self::PROMOTE_TO_SHIFT)
+    }
+
+    pub fn target_square(self) -> Square {
+        (self.0.get() & Self::SQUARE_MASK) as Square
+    }
+
+    pub fn origin_square(self) -> Square {
+        (self.0.get() >> Self::FROM_SHIFT & Self::SQUARE_MASK) as Square
+    }
+
+    pub fn to(self) -> Position {
+        position(self.target_square())
+    }
+
+    /// Origin square (equal to `to` for drops)
+    pub fn from(self) -> Position {
+        position(self.origin_square())
+    }
+
+    pub fn piece_type(self) -> PieceType {
+        PieceType::ALL[(self.0.get() >> Self::PIECE_SHIFT & Self::TYPE_MASK) as usize]
+    }
+
+    pub fn is_drop(self) -> bool {
+        self.0.get() & Self::DROP != 0
+    }
+
+    /// Whether this move promotes the piece
+    pub fn is_promotion(self) -> bool {
+        self.0.get() & Self::PROMOTION != 0
+    }
+
+    /// Whether the moving piece was promoted before the move
+    pub fn was_promoted(self) -> bool {
+        self.0.get() & Self::PROMOTED != 0
+    }
+
+    /// Chess-style promotion target
+    pub fn promote_to(self) -> Option<PieceType> {
+        match self.0.get() >> Self::PROMOTE_TO_SHIFT & Self::TYPE_MASK {
+            0 => None,
+            idx => Some(PieceType::ALL[idx as usize - 1]),
+        }
+    }
+
+    /// The packed representation
+    pub fn bits(self) -> u32 {
+        self.0.get()
+    }
+
+    /// Unpack a move saved with `bits`, rejecting values no move packs to
+    pub fn from_bits(bits: u32) -> Option<Self> {
+        let used = Self::VALID
+            | Self::PROMOTED
+            | Self::PROMOTION
+            | Self::DROP
+            | Self::TYPE_MASK << Self::PROMOTE_TO_SHIFT
+            | Self::TYPE_MASK << Self::PIECE_SHIFT
+            | Self::SQUARE_MASK << Self::FROM_SHIFT
+            | Self::SQUARE_MASK;
+        let m = Self(NonZeroU32::new(bits)?);
+        let valid = bits & Self::VALID != 0
+            && bits & !used == 0
+            && m.target_square() < NUM_SQUARES
+            && m.origin_square() < NUM_SQUARES
+            && (bits >> Self::PIECE_SHIFT & Self::TYPE_MASK) < NUM_PIECE_TYPES as u32
+            && (bits >> Self::PROMOTE_TO_SHIFT & Self::TYPE_MASK) <= NUM_PIECE_TYPES as u32;
+        valid.then_some(m)
+    }
+}
+
+impl fmt::Debug for Move {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Move")
+            .field("from", &self.from())
+            .field("to", &self.to())
+            .field("piece_type", &self.piece_type())
+            .field("promoted", &self.was_promoted())
+            .field("promotion", &self.is_promotion())
+            .field("drop", &self.is_drop())
+            .field("promote_to", &self.promote_to())
+            .finish()
+    }
 }
 
 /// Input format from JavaScript
@@ -74,6 +282,13 @@ pub struct GameStateInput {
     #[serde(rename = "currentPlayer")]
     pub current_player: Player,
     pub hands: Option<HandPieces>,
+    pub rules: Option<RuleSet>,
+    /// Plies played so far (for move-limit draws)
+    #[serde(rename = "moveCount", default)]
+    pub move_count: u32,
+    /// Checks given so far by player 1 and player 2 (for three-check)
+    #[serde(rename = "checksGiven", default)]
+    pub checks_given: [u8; 2],
 }
 
 /// Hand pieces (captured pieces)
@@ -83,25 +298,78 @@ pub struct HandPieces {
     pub player2: Vec<PieceType>,
 }
 
+/// Search score for the side to move
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(tag = "type", content = "value", rename_all = "lowercase")]
+pub enum Score {
+    /// Centipawns
+    Cp(i32),
+    /// Moves until mate; negative when getting mated
+    Mate(i32),
+}
+
+/// A root move's score and expected continuation
+#[derive(Debug, Serialize)]
+pub struct LineOutput {
+    pub score: Score,
+    pub pv: Vec<MoveOutput>,
+}
+
+/// Best move with what the search expects to follow
+#[derive(Debug, Serialize)]
+pub struct SearchOutput {
+    #[serde(flatten)]
+    pub best_move: MoveOutput,
+    #[serde(flatten)]
+    pub line: LineOutput,
+    pub depth: u8,
+}
+
+/// Several best lines for analysis, best first
+#[derive(Debug, Serialize)]
+pub struct AnalysisOutput {
+    pub depth: u8,
+    pub lines: Vec<LineOutput>,
+}
+
+/// Search progress sent to JavaScript
+#[derive(Debug, Serialize)]
+pub struct InfoOutput {
+    pub multipv: usize,
+    pub depth: u8,
+    pub seldepth: usize,
+    pub score: Score,
+    pub bound: Bound,
+    pub nodes: usize,
+    pub nps: u64,
+    #[serde(rename = "timeMs")]
+    pub time_ms: u64,
+    pub hashfull: u32,
+    pub pv: Vec<MoveOutput>,
+}
+
 /// Output format to JavaScript
 #[derive(Debug, Serialize)]
 pub struct MoveOutput {
-    pub from: Position,
+    pub from: Option<Position>, // None for drops
     pub to: Position,
     #[serde(rename = "pieceType")]
     pub piece_type: PieceType,
     pub promoted: bool,
     pub promotion: bool,
+    #[serde(rename = "promoteTo", skip_serializing_if = "Option::is_none")]
+    pub promote_to: Option<PieceType>,
 }
 
 impl MoveOutput {
     pub fn from_move(m: &Move) -> Self {
         Self {
-            from: m.from,
-            to: m.to,
-            piece_type: m.piece_type,
-            promoted: m.promoted,
-            promotion: m.promotion,
+            from: if m.is_drop() { None } else { Some(m.from()) },
+            to: m.to(),
+            piece_type: m.piece_type(),
+            promoted: m.was_promoted(),
+            promotion: m.is_promotion(),
+            promote_to: m.promote_to(),
         }
     }
 }
@@ -110,3 +378,100 @@ impl MoveOutput {
 pub fn to_js_error(msg: &str) -> JsValue {
     JsValue::from_str(msg)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pos(row: usize, col: usize) -> Position {
+        Position { row, col }
+    }
+
+    fn piece(piece_type: PieceType, promoted: bool) -> Piece {
+        Piece {
+            piece_type,
+            player: 1,
+            promoted,
+        }
+    }
+
+    /// `m` survives `bits`/`from_bits` unchanged
+    fn round_trip(m: Move) -> Move {
+        let unpacked = Move::from_bits(m.bits()).expect("packed move unpacks");
+        assert_eq!(unpacked, m);
+        unpacked
+    }
+
+    #[test]
+    fn board_move_round_trips() {
+        let m = round_trip(Move::new(
+            pos(6, 2),
+            pos(5, 2),
+            &piece(PieceType::Pawn, false),
+        ));
+        assert_eq!((m.from(), m.to()), (pos(6, 2), pos(5, 2)));
+        assert_eq!(m.piece_type(), PieceType::Pawn);
+        assert!(!m.is_drop() && !m.is_promotion() && !m.was_promoted());
+        assert_eq!(m.promote_to(), None);
+    }
+
+    #[test]
+    fn drop_round_trips() {
+        let m = round_trip(Move::drop(PieceType::Gold, pos(4, 4)));
+        assert!(m.is_drop());
+        assert_eq!((m.from(), m.to()), (pos(4, 4), pos(4, 4)));
+        assert_eq!(m.piece_type(), PieceType::Gold);
+    }
+
+    #[test]
+    fn promotions_round_trip() {
+        let base = Move::new(pos(3, 1), pos(2, 1), &piece(PieceType::Silver, false));
+        let m = round_trip(base.with_promotion());
+        assert!(m.is_promotion() && !m.was_promoted());
+
+        let m = round_trip(Move::new(
+            pos(2, 1),
+            pos(1, 1),
+            &piece(PieceType::Silver, true),
+        ));
+        assert!(m.was_promoted() && !m.is_promotion());
+
+        for target in [
+            PieceType::ChessQueen,
+            PieceType::ChessKnight,
+            PieceType::Gold,
+        ] {
+            let pawn = Move::new(pos(1, 0), pos(0, 0), &piece(PieceType::ChessPawn, false));
+            let m = round_trip(pawn.with_promote_to(target));
+            assert_eq!(m.promote_to(), Some(target));
+            assert_eq!(m.piece_type(), PieceType::ChessPawn);
+        }
+    }
+
+    #[test]
+    fn corner_squares_of_11x11_round_trip() {
+        for (from, to) in [
+            (pos(0, 0), pos(10, 10)),
+            (pos(10, 10), pos(0, 0)),
+            (pos(10, 0), pos(0, 10)),
+        ] {
+            for piece_type in PieceType::ALL {
+                let m = round_trip(Move::new(from, to, &piece(piece_type, true)));
+                assert_eq!((m.from(), m.to(), m.piece_type()), (from, to, piece_type));
+            }
+            let m = round_trip(Move::drop(PieceType::ChessKing, to));
+            assert_eq!(m.to(), to);
+        }
+    }
+
+    #[test]
+    fn from_bits_rejects_invalid_values() {
+        let valid = Move::drop(PieceType::Pawn, pos(0, 0)).bits();
+        assert!(Move::from_bits(0).is_none());
+        // Missing marker bit, unused bits, square past the board, bad piece type
+        assert!(Move::from_bits(valid & !(1 << 31)).is_none());
+        assert!(Move::from_bits(valid | 1 << 25).is_none());
+        assert!(Move::from_bits(valid | 0x7f).is_none());
+        assert!(Move::from_bits(valid | 0xf << 14).is_none());
+    }
+}
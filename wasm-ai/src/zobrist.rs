@@ -1,20 +1,37 @@
+use crate::bitboard::{square, NUM_SQUARES};
 use crate::board::Board;
+use crate::rules::MAX_CHECK_LIMIT;
 use crate::types::*;
+use std::sync::OnceLock;
+
+/// Seed of the key generator (saved with exported hash tables)
+pub const KEY_SEED: u64 = 0x123456789abcdef0;
+
+/// Every piece type in both its unpromoted and promoted state
+const NUM_PIECE_STATES: usize = NUM_PIECE_TYPES * 2;
 
 /// Zobrist hashing for position keys
 pub struct ZobristHasher {
-    // Hash values for each piece type, position, and player
-    // Format: [player][piece_type][position]
-    piece_keys: [[[u64; 81]; 11]; 2],
+    // Hash values for each piece type and promotion state, position, and player
+    // Format: [player][piece_type * 2 + promoted][position]
+    piece_keys: [[[u64; NUM_SQUARES]; NUM_PIECE_STATES]; 2],
     // Hash for player to move
     player_keys: [u64; 2],
+    // Key for the n-th copy of a piece type in hand
+    // Format: [player][piece_type][count - 1]
+    hand_keys: [[[u64; NUM_SQUARES]; NUM_PIECE_TYPES]; 2],
+    // Key for the n-th check given (three-check)
+    // Format: [player][count - 1]
+    check_keys: [[u64; MAX_CHECK_LIMIT as usize]; 2],
 }
 
 impl ZobristHasher {
     pub fn new() -> Self {
         let mut hasher = ZobristHasher {
-            piece_keys: [[[0; 81]; 11]; 2],
+            piece_keys: [[[0; NUM_SQUARES]; NUM_PIECE_STATES]; 2],
             player_keys: [0; 2],
+            hand_keys: [[[0; NUM_SQUARES]; NUM_PIECE_TYPES]; 2],
+            check_keys: [[0; MAX_CHECK_LIMIT as usize]; 2],
         };
         hasher.init_random_keys();
         hasher
@@ -22,15 +39,15 @@ impl ZobristHasher {
 
     fn init_random_keys(&mut self) {
         // Simple LCG for deterministic random numbers
-        let mut rng = 0x123456789abcdef0u64;
+        let mut rng = KEY_SEED;
 
         for player in 0..2 {
-            for piece_type in 0..11 {
-                for pos in 0..81 {
+            for piece_state in 0..NUM_PIECE_STATES {
+                for pos in 0..NUM_SQUARES {
                     rng = rng
                         .wrapping_mul(6364136223846793005)
                         .wrapping_add(1442695040888963407);
-                    self.piece_keys[player][piece_type][pos] = rng;
+                    self.piece_keys[player][piece_state][pos] = rng;
                 }
             }
         }
@@ -41,57 +58,98 @@ impl ZobristHasher {
                 .wrapping_add(1442695040888963407);
             self.player_keys[player] = rng;
         }
+
+        for player in 0..2 {
+            for piece_type in 0..NUM_PIECE_TYPES {
+                for count in 0..NUM_SQUARES {
+                    rng = rng
+                        .wrapping_mul(6364136223846793005)
+                        .wrapping_add(1442695040888963407);
+                    self.hand_keys[player][piece_type][count] = rng;
+                }
+            }
+        }
+
+        for player in 0..2 {
+            for count in 0..MAX_CHECK_LIMIT as usize {
+                rng = rng
+                    .wrapping_mul(6364136223846793005)
+                    .wrapping_add(1442695040888963407);
+                self.check_keys[player][count] = rng;
+            }
+        }
+    }
+
+    /// Value that changes whenever the key layout or generator changes
+    pub fn fingerprint(&self) -> u64 {
+        self.piece_keys[1][NUM_PIECE_STATES - 1][NUM_SQUARES - 1]
+            ^ self.player_keys[1]
+            ^ self.hand_keys[1][NUM_PIECE_TYPES - 1][NUM_SQUARES - 1]
+            ^ self.check_keys[1][MAX_CHECK_LIMIT as usize - 1]
+    }
+
+    /// Key of a piece standing on a square
+    pub fn piece_key(&self, piece: &Piece, pos: Position) -> u64 {
+        let piece_state = piece.piece_type.index() * 2 + piece.promoted as usize;
+        self.piece_keys[(piece.player - 1) as usize][piece_state][square(pos)]
     }
 
+    /// Key toggled when a player's hand goes from `count - 1` to `count`
+    /// pieces of a type (or back)
+    pub fn hand_key(&self, player: Player, piece_type: PieceType, count: usize) -> u64 {
+        self.hand_keys[(player - 1) as usize][piece_type.index()][count - 1]
+    }
+
+    /// Key toggled when a player's checks given go from `count - 1` to `count`
+    pub fn check_key(&self, player: Player, count: u8) -> u64 {
+        self.check_keys[(player - 1) as usize][(count - 1) as usize]
+    }
+
+    /// Key toggled when the side to move changes
+    pub fn side_key(&self) -> u64 {
+        self.player_keys[0] ^ self.player_keys[1]
+    }
+
+    /// Full hash of a board; `Board::hash` keeps the same value incrementally
     pub fn hash(&self, board: &Board) -> u64 {
         let mut hash = 0u64;
 
         // Hash all pieces on the board
-        for row in 0..board.size() {
-            for col in 0..board.size() {
-                if let Some(piece) = board.get(Position { row, col }) {
-                    let player_idx = (piece.player - 1) as usize;
-                    let piece_idx = piece_type_to_index(&piece.piece_type);
-                    let pos_idx = row * 9 + col;
-                    hash ^= self.piece_keys[player_idx][piece_idx][pos_idx];
+        for row in 0..board.rows() {
+            for col in 0..board.cols() {
+                let pos = Position { row, col };
+                if let Some(piece) = board.get(pos) {
+                    hash ^= self.piece_key(piece, pos);
+                }
+            }
+        }
+
+        // Hash pieces in hand
+        for player in [1, 2] {
+            for piece_type in PieceType::ALL {
+                for n in 1..=board.hand_count(player, piece_type) as usize {
+                    hash ^= self.hand_key(player, piece_type, n);
                 }
             }
         }
 
+        // Hash checks given (only non-zero under three-check)
+        for player in [1, 2] {
+            for n in 1..=board.checks_given[(player - 1) as usize] {
+                hash ^= self.check_key(player, n);
+            }
+        }
+
         // Hash current player
-        hash ^= self.player_keys[(board.current_player - 1) as usize];
+        hash ^= self.player_keys[(board.current_player() - 1) as usize];
 
         hash
     }
 }
 
-fn piece_type_to_index(piece_type: &PieceType) -> usize {
-    match piece_type {
-        PieceType::King => 0,
-        PieceType::Rook => 1,
-        PieceType::Bishop => 2,
-        PieceType::Gold => 3,
-        PieceType::Silver => 4,
-        PieceType::Knight => 5,
-        PieceType::Lance => 6,
-        PieceType::Pawn => 7,
-        PieceType::ChessKing => 0,
-        PieceType::ChessQueen => 8,
-        PieceType::ChessRook => 9,
-        PieceType::ChessBishop => 10,
-        PieceType::ChessKnight => 5,
-        PieceType::ChessPawn => 7,
-    }
-}
-
 // Global static instance (lazy initialization)
-static mut ZOBRIST: Option<ZobristHasher> = None;
+static ZOBRIST: OnceLock<ZobristHasher> = OnceLock::new();
 
 pub fn get_zobrist() -> &'static ZobristHasher {
-    unsafe {
-        if ZOBRIST.is_none() {
-            ZOBRIST = Some(ZobristHasher::new());
-        }
-        ZOBRIST.as_ref().unwrap()
-    }
+    ZOBRIST.get_or_init(ZobristHasher::new)
 }
@@ -29,7 +29,16 @@ interface SetDepthMessage {
   depth: number;
 }
 
-type WorkerMessage = InitMessage | GetMoveMessage | SetLevelMessage | SetDepthMessage;
+interface NewGameMessage {
+  type: 'NEW_GAME';
+}
+
+type WorkerMessage =
+  | InitMessage
+  | GetMoveMessage
+  | SetLevelMessage
+  | SetDepthMessage
+  | NewGameMessage;
 
 // Response types
 interface ReadyResponse {
@@ -41,12 +50,17 @@ interface MoveResponse {
   move: string;
 }
 
+interface InfoResponse {
+  type: 'INFO';
+  info: string;
+}
+
 interface ErrorResponse {
   type: 'ERROR';
   error: string;
 }
 
-type WorkerResponse = ReadyResponse | MoveResponse | ErrorResponse;
+type WorkerResponse = ReadyResponse | MoveResponse | InfoResponse | ErrorResponse;
 
 // Handle messages from main thread
 self.onmessage = async (e: MessageEvent<WorkerMessage>) => {
@@ -70,6 +84,10 @@ self.onmessage = async (e: MessageEvent<WorkerMessage>) => {
         handleSetDepth(e.data.depth);
         break;
 
+      case 'NEW_GAME':
+        handleNewGame();
+        break;
+
       default:
         postError(`Unknown message type: ${type}`);
     }
@@ -86,6 +104,12 @@ async function handleInit(level: number) {
     // Create AI instance with level
     wasmAI = new wasmModule.WasmAI(level);
 
+    // Forward search progress while a move is being computed
+    wasmAI.set_info_callback((infoJson: string) => {
+      const response: InfoResponse = { type: 'INFO', info: infoJson };
+      self.postMessage(response);
+    });
+
     // Notify main thread that worker is ready
     const response: ReadyResponse = { type: 'READY' };
     self.postMessage(response);
@@ -155,6 +179,19 @@ function handleSetDepth(depth: number) {
   }
 }
 
+function handleNewGame() {
+  if (!wasmAI) {
+    postError('AI not initialized. Call INIT first.');
+    return;
+  }
+
+  try {
+    wasmAI.new_game();
+  } catch (error) {
+    postError(`Failed to start new game: ${error}`);
+  }
+}
+
 function postError(errorMessage: string) {
   const response: ErrorResponse = {
     type: 'ERROR',